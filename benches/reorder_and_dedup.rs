@@ -0,0 +1,58 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+type DecodedTags = gigtag::DecodedTags<
+    gigtag::facet::CompactFacet,
+    gigtag::label::CompactLabel,
+    gigtag::props::CompactName,
+    String,
+>;
+
+fn encoded_tags(count: usize) -> String {
+    (0..count)
+        .map(|i| {
+            format!(
+                "facet{}@2022{:02}{:02}#label{i}",
+                i % 7,
+                i % 12 + 1,
+                i % 28 + 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn reorder_and_dedup(c: &mut Criterion) {
+    let unsorted = DecodedTags::decode_str(&encoded_tags(1000));
+    let mut sorted = unsorted.clone();
+    sorted.reorder_and_dedup();
+    assert!(sorted.is_already_canonical());
+
+    c.bench_function("reorder_and_dedup unsorted", |b| {
+        b.iter_batched(
+            || unsorted.clone(),
+            |mut tags| {
+                tags.reorder_and_dedup();
+                black_box(tags)
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("reorder_and_dedup already canonical", |b| {
+        b.iter_batched(
+            || sorted.clone(),
+            |mut tags| {
+                tags.reorder_and_dedup();
+                black_box(tags)
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+criterion_group!(benches, reorder_and_dedup);
+criterion_main!(benches);
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+//! Compact binary serialization of decoded tags
+//!
+//! The binary format is intended for caching decoded tags and is
+//! not supposed to be used for storage or interchange. Use the
+//! textual encoding for these purposes.
+//!
+//! All strings are stored as UTF-8, prefixed by their length. All
+//! lengths and counts are encoded as 32-bit unsigned integers in
+//! little-endian byte order. The first byte contains the format
+//! version.
+
+use anyhow::anyhow;
+
+use crate::{
+    facet, label, props, DecodeError, DecodedTags, Facet, Label, Name, Property, Tag,
+    TagInvalidity, Value,
+};
+
+/// The current version of the binary format.
+pub const FORMAT_VERSION: u8 = 1;
+
+impl<F, L, N, V> DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+    V: Value,
+{
+    /// Serialize into the binary format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a string or the number of tags or properties
+    /// exceeds the range of 32-bit unsigned integers.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![FORMAT_VERSION];
+        write_str(&mut bytes, &self.undecoded_prefix);
+        write_len(&mut bytes, self.tags.len());
+        for tag in &self.tags {
+            write_str(&mut bytes, tag.facet().as_ref());
+            write_str(&mut bytes, tag.label().as_ref());
+            write_len(&mut bytes, tag.props().len());
+            for prop in tag.props() {
+                write_str(&mut bytes, prop.name().as_ref());
+                write_str(&mut bytes, prop.value().as_ref());
+            }
+        }
+        bytes
+    }
+
+    /// Deserialize from the binary format.
+    ///
+    /// All components are validated before constructing the tags.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the input is malformed, has an
+    /// unsupported format version, or contains invalid tags.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        let version = reader.read_u8()?;
+        if version != FORMAT_VERSION {
+            return Err(DecodeError::Parse(anyhow!(
+                "unsupported binary format version {version}"
+            )));
+        }
+        let undecoded_prefix = reader.read_str()?.to_owned();
+        let tag_count = reader.read_len()?;
+        let mut tags = Vec::with_capacity(tag_count.min(reader.bytes.len()));
+        for _ in 0..tag_count {
+            let facet = reader.read_str()?;
+            facet::validate(facet)
+                .map_err(TagInvalidity::Facet)
+                .map_err(invalid_tag)?;
+            let label = reader.read_str()?;
+            label::validate(label)
+                .map_err(TagInvalidity::Label)
+                .map_err(invalid_tag)?;
+            let prop_count = reader.read_len()?;
+            let mut props = Vec::with_capacity(prop_count.min(reader.bytes.len()));
+            for _ in 0..prop_count {
+                let name = reader.read_str()?;
+                if name.is_empty() || !props::is_name_valid(name) {
+                    return Err(invalid_tag(TagInvalidity::PropName));
+                }
+                let value = reader.read_str()?;
+                props.push(Property {
+                    name: N::from_str(name),
+                    value: V::from_str(value),
+                });
+            }
+            let tag = Tag {
+                label: L::from_str(label),
+                facet: F::from_str(facet),
+                props,
+            };
+            tag.validate().map_err(invalid_tag)?;
+            tags.push(tag);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::Parse(anyhow!("trailing bytes")));
+        }
+        Ok(Self {
+            tags,
+            undecoded_prefix,
+        })
+    }
+}
+
+fn invalid_tag(err: TagInvalidity) -> DecodeError {
+    DecodeError::Parse(err.into())
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("length exceeds 32 bits");
+    bytes.extend_from_slice(&len.to_le_bytes());
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    write_len(bytes, s.len());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::Parse(anyhow!("unexpected end of input")));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn read_len(&mut self) -> Result<usize, DecodeError> {
+        let bytes = self.read_bytes(4)?;
+        let len = u32::from_le_bytes(bytes.try_into().expect("4 bytes"));
+        usize::try_from(len).map_err(|err| DecodeError::Parse(err.into()))
+    }
+
+    fn read_str(&mut self) -> Result<&'a str, DecodeError> {
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+        std::str::from_utf8(bytes).map_err(|err| DecodeError::Parse(err.into()))
+    }
+}
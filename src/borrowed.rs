@@ -0,0 +1,201 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+//! Decoding of tags that borrow from the encoded input
+
+use std::borrow::Cow;
+
+use crate::{
+    check_encoded_token, decode_components, decode_trailing_tokens, facet, BorrowedProperty,
+    DecodeError, DecodeOptions, DecodedTags, Facet, Label, Name, StdFacet, StdLabel, StdName, Tag,
+    Value,
+};
+
+/// A tag that borrows from the encoded input
+///
+/// Components without percent-encoded characters are borrowed
+/// from the encoded input without allocating.
+///
+/// See also: [`Tag`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagRef<'a> {
+    /// The label
+    pub label: Cow<'a, str>,
+
+    /// The facet
+    pub facet: Cow<'a, str>,
+
+    /// The properties
+    pub props: Vec<BorrowedProperty<'a>>,
+}
+
+impl<'a> TagRef<'a> {
+    /// Decode a tag from an encoded token without copying.
+    ///
+    /// Accepts the same input as [`Tag::decode_str()`]. Tokens that
+    /// are normalized when parsed as an URL are decoded into owned
+    /// components.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag.
+    pub fn decode_str(encoded: &'a str) -> Result<Self, DecodeError> {
+        if needs_url_normalization(encoded) {
+            return Tag::<StdFacet, StdLabel, StdName, String>::decode_str(encoded)
+                .map(Self::from_tag);
+        }
+        check_encoded_token(encoded)?;
+        let (path_query, fragment) = split_fragment(encoded);
+        let (path, query) = path_query.split_once('?').unwrap_or((path_query, ""));
+        let tag = decode_components(path, query, fragment, &DecodeOptions::default())?;
+        if !tag.is_valid() {
+            return Err(DecodeError::InvalidTag);
+        }
+        Ok(tag)
+    }
+
+    /// Check if the tag is valid.
+    ///
+    /// See also: [`Tag::is_valid()`]
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.label.is_empty()
+            || (!self.facet.is_empty()
+                && (!self.props.is_empty() || facet::has_date_like_suffix(&self.facet)))
+    }
+
+    /// Check if all components are borrowed from the encoded input.
+    #[must_use]
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.label, Cow::Borrowed(_))
+            && matches!(self.facet, Cow::Borrowed(_))
+            && self.props.iter().all(|prop| {
+                matches!(prop.name, Cow::Borrowed(_)) && matches!(prop.value, Cow::Borrowed(_))
+            })
+    }
+
+    /// Convert into a tag that owns its components.
+    ///
+    /// Owned components are moved without copying.
+    #[must_use]
+    pub fn into_owned<F, L, N, V>(self) -> Tag<F, L, N, V>
+    where
+        F: Facet,
+        L: Label,
+        N: Name,
+        V: Value,
+    {
+        let Self {
+            label,
+            facet,
+            props,
+        } = self;
+        Tag {
+            label: L::from_cow_str(label),
+            facet: F::from_cow_str(facet),
+            props: props
+                .into_iter()
+                .map(BorrowedProperty::into_owned)
+                .collect(),
+        }
+    }
+
+    fn from_tag(tag: Tag<StdFacet, StdLabel, StdName, String>) -> Self {
+        let Tag {
+            label,
+            facet,
+            props,
+        } = tag;
+        Self {
+            label: Cow::Owned(label.into()),
+            facet: Cow::Owned(facet.into()),
+            props: props
+                .into_iter()
+                .map(|prop| BorrowedProperty {
+                    name: Cow::Owned(prop.name.into()),
+                    value: Cow::Owned(prop.value),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Tags that borrow from the encoded input
+///
+/// The borrowed counterpart of [`DecodedTags`].
+///
+/// The functions are named [`Self::decode_str()`] and [`Self::into_owned()`]
+/// for consistency with [`DecodedTags::decode_str()`] and
+/// [`BorrowedProperty::into_owned()`]. A method named `to_owned()` would
+/// be confused with [`ToOwned::to_owned()`] that returns a clone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedTagsRef<'a> {
+    /// The decoded tags
+    pub tags: Vec<TagRef<'a>>,
+
+    /// The undecoded prefix
+    pub undecoded_prefix: &'a str,
+}
+
+impl<'a> DecodedTagsRef<'a> {
+    /// Decode tags from a text field without copying.
+    ///
+    /// Tokenizes the input in the same way as [`DecodedTags::decode_str()`].
+    #[must_use]
+    pub fn decode_str(encoded: &'a str) -> Self {
+        let (tags, undecoded_prefix) = decode_trailing_tokens(
+            encoded,
+            DecodeOptions::default().stop_at_newline,
+            |token| TagRef::decode_str(token).ok(),
+            |_| (),
+        );
+        Self {
+            tags,
+            undecoded_prefix,
+        }
+    }
+
+    /// Convert into decoded tags that own their contents.
+    #[must_use]
+    pub fn into_owned<F, L, N, V>(self) -> DecodedTags<F, L, N, V>
+    where
+        F: Facet,
+        L: Label,
+        N: Name,
+        V: Value,
+    {
+        let Self {
+            tags,
+            undecoded_prefix,
+        } = self;
+        DecodedTags {
+            tags: tags.into_iter().map(TagRef::into_owned).collect(),
+            undecoded_prefix: undecoded_prefix.to_owned(),
+        }
+    }
+}
+
+/// Check if parsing the token as an URL would normalize its contents.
+///
+/// This includes stripped control characters, scheme-like prefixes,
+/// backslashes that are treated like slashes, and dot segments in
+/// the path.
+fn needs_url_normalization(encoded: &str) -> bool {
+    if encoded.chars().any(|c| c.is_ascii_control()) {
+        return true;
+    }
+    let (path_query, _) = split_fragment(encoded);
+    let path = path_query
+        .split_once('?')
+        .map_or(path_query, |(path, _)| path);
+    path.contains(':')
+        || path.contains('\\')
+        || path.split('/').any(|segment| {
+            let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+            segment == "." || segment == ".."
+        })
+}
+
+fn split_fragment(encoded: &str) -> (&str, &str) {
+    encoded.split_once('#').unwrap_or((encoded, ""))
+}
@@ -0,0 +1,179 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+//! Percent-encoding of components
+
+use percent_encoding::{percent_encode, AsciiSet, CONTROLS};
+
+use crate::{decode_component, DecodeError};
+
+const CONTROLS_ESCAPE: &AsciiSet = &CONTROLS.add(b'%');
+
+/// <https://url.spec.whatwg.org/#fragment-percent-encode-set>
+const FRAGMENT: &AsciiSet = &CONTROLS_ESCAPE
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`');
+
+pub(crate) const LABEL: &AsciiSet = FRAGMENT;
+
+/// <https://url.spec.whatwg.org/#query-percent-encode-set>
+const QUERY: &AsciiSet = &CONTROLS_ESCAPE
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'#');
+
+pub(crate) const PROPS: &AsciiSet = &QUERY.add(b'&').add(b'=');
+
+/// Escapes the alternative `;` property separator in addition to [`PROPS`].
+pub(crate) const PROPS_SEMICOLON_SEPARATED: &AsciiSet = &PROPS.add(b';');
+
+/// <https://url.spec.whatwg.org/#path-percent-encode-set>
+const PATH: &AsciiSet = &QUERY.add(b'`').add(b'?').add(b'{').add(b'}');
+
+pub(crate) const FACET: &AsciiSet = PATH;
+
+/// Check if an encoded component contains unnecessarily escaped characters.
+///
+/// A character is over-encoded if it is percent-encoded in `encoded`
+/// even though it is not contained in `ascii_set` and thus would
+/// have been preserved verbatim when encoding. Escapes with lowercase
+/// hex digits are also considered as over-encoded, because encoding
+/// always produces uppercase hex digits.
+pub(crate) fn is_overencoded(encoded: &[u8], ascii_set: &'static AsciiSet) -> bool {
+    encoded
+        .split(|b| *b == b'%')
+        .skip(1)
+        .filter_map(|escaped| {
+            let hex = escaped.get(..2)?;
+            let byte = u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+            Some((hex, byte))
+        })
+        .any(|(hex, byte)| hex.iter().any(u8::is_ascii_lowercase) || !is_escaped(byte, ascii_set))
+}
+
+fn is_escaped(byte: u8, ascii_set: &'static AsciiSet) -> bool {
+    // Non-ASCII bytes are always escaped
+    !byte.is_ascii()
+        || percent_encode(&[byte], ascii_set)
+            .next()
+            .is_some_and(|encoded| encoded.len() > 1)
+}
+
+/// Components of a tag that are encoded separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Component {
+    /// The facet
+    Facet,
+
+    /// The label
+    Label,
+
+    /// A property name
+    PropName,
+
+    /// A property value
+    PropValue,
+}
+
+impl Component {
+    const fn ascii_set(self) -> &'static AsciiSet {
+        match self {
+            Self::Facet => FACET,
+            Self::Label => LABEL,
+            Self::PropName | Self::PropValue => PROPS,
+        }
+    }
+}
+
+/// A character that is percent-encoded when encoding a component
+///
+/// See also: [`Tag::encoding_report()`](crate::Tag::encoding_report)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapedChar {
+    /// The component that contains the character.
+    pub component: Component,
+
+    /// The index of the property for [`Component::PropName`] and
+    /// [`Component::PropValue`], otherwise `None`.
+    pub prop_index: Option<usize>,
+
+    /// The byte position of the character within the component.
+    pub position: usize,
+
+    /// The character.
+    pub character: char,
+
+    /// The percent-encoded replacement, e.g. `%20` for a space.
+    pub escaped: String,
+}
+
+/// Collect all characters of a component that are percent-encoded.
+pub(crate) fn escaped_chars(
+    component: Component,
+    prop_index: Option<usize>,
+    input: &str,
+) -> impl Iterator<Item = EscapedChar> + '_ {
+    let ascii_set = component.ascii_set();
+    input
+        .char_indices()
+        .filter_map(move |(position, character)| {
+            let mut utf8 = [0; 4];
+            let utf8 = character.encode_utf8(&mut utf8);
+            let escaped = percent_encode(utf8.as_bytes(), ascii_set).to_string();
+            (escaped != *utf8).then_some(EscapedChar {
+                component,
+                prop_index,
+                position,
+                character,
+                escaped,
+            })
+        })
+}
+
+/// Return the ASCII characters that are percent-encoded for a component.
+///
+/// The bytes are returned in ascending order. All non-ASCII bytes
+/// are always percent-encoded and not included in the result.
+#[must_use]
+pub fn escaped_bytes(component: Component) -> Vec<u8> {
+    let ascii_set = component.ascii_set();
+    (0..=0x7f)
+        .filter(|byte| is_escaped(*byte, ascii_set))
+        .collect()
+}
+
+/// Percent-encode a component and append it to a buffer.
+///
+/// Allows to reuse the buffer for encoding many components without
+/// allocating temporary strings.
+pub fn encode_component_into(component: Component, input: &str, buf: &mut String) {
+    buf.extend(percent_encode(input.as_bytes(), component.ascii_set()));
+}
+
+/// Percent-encode a facet and append it to a buffer.
+///
+/// See also: [`encode_component_into()`]
+pub fn encode_facet_into(facet: &str, buf: &mut String) {
+    encode_component_into(Component::Facet, facet, buf);
+}
+
+/// Percent-decode a component and append it to a buffer.
+///
+/// The buffer remains unmodified on error.
+///
+/// # Errors
+///
+/// Returns a [`DecodeError`] if the decoded component is not valid UTF-8.
+pub fn decode_component_into(encoded: &str, buf: &mut String) -> Result<(), DecodeError> {
+    // Only the decoded component is validated, not the preceding contents
+    // of the buffer. A temporary string is only allocated for components
+    // that contain escaped characters.
+    let decoded = decode_component(encoded.as_bytes(), false)?;
+    buf.push_str(&decoded);
+    Ok(())
+}
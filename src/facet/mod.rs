@@ -3,11 +3,89 @@
 
 //! Facets
 
-use std::{borrow::Cow, fmt, ops::Deref, sync::OnceLock};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt,
+    ops::{Deref, RangeInclusive},
+    sync::OnceLock,
+};
 
 use compact_str::{CompactString, ToCompactString as _};
+use derive_more::{Display, Error};
 use regex::bytes::Regex;
-use time::{format_description::FormatItem, macros::format_description, Date};
+use time::{
+    format_description::FormatItem,
+    macros::{date, format_description},
+    Date, OffsetDateTime,
+};
+
+/// Reasons for an invalid facet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[allow(clippy::module_name_repetitions)]
+pub enum FacetInvalidity {
+    /// Leading or trailing whitespace.
+    #[display("leading or trailing whitespace")]
+    LeadingOrTrailingWhitespace,
+
+    /// Leading slash `/`.
+    #[display("leading slash")]
+    LeadingSlash,
+
+    /// Date-like suffix preceded by whitespace.
+    #[display("date-like suffix preceded by whitespace")]
+    InvalidDateLikeSuffix,
+
+    /// Empty hierarchy segment, a segment containing a slash `/`,
+    /// or with leading/trailing whitespace.
+    #[display("invalid segment")]
+    InvalidSegment,
+}
+
+/// Validate the given facet.
+///
+/// An empty facet is valid.
+///
+/// In contrast to [`is_valid()`] this function also rejects facets
+/// with an invalid date-like suffix, see [`has_invalid_date_like_suffix()`].
+///
+/// # Errors
+///
+/// Returns a [`FacetInvalidity`] if the facet is invalid.
+pub fn validate(facet: &str) -> Result<(), FacetInvalidity> {
+    if facet.trim() != facet {
+        return Err(FacetInvalidity::LeadingOrTrailingWhitespace);
+    }
+    if facet.as_bytes().first() == Some(&b'/') {
+        return Err(FacetInvalidity::LeadingSlash);
+    }
+    if has_invalid_date_like_suffix(facet) {
+        return Err(FacetInvalidity::InvalidDateLikeSuffix);
+    }
+    Ok(())
+}
+
+/// Coerce arbitrary input into a valid facet.
+///
+/// Trims leading/trailing whitespace and strips leading slashes `/`.
+/// Whitespace between the prefix and a date-like suffix is removed.
+/// The result is always valid, but might be empty.
+#[must_use]
+pub fn coerce(input: &str) -> String {
+    let mut facet = input.trim();
+    while let Some(stripped) = facet.strip_prefix('/') {
+        facet = stripped.trim_start();
+    }
+    debug_assert!(is_valid(facet));
+    if !has_invalid_date_like_suffix(facet) {
+        return facet.to_owned();
+    }
+    // The regex only matches ASCII characters at the end
+    let (prefix, date_like_suffix) = facet.split_at(facet.len() - DATE_LIKE_SUFFIX_LEN);
+    let coerced = format!("{prefix}{date_like_suffix}", prefix = prefix.trim_end());
+    debug_assert!(validate(&coerced).is_ok());
+    coerced
+}
 
 /// Check if the given facet is valid.
 ///
@@ -24,7 +102,30 @@ pub fn is_empty(facet: &str) -> bool {
     facet.is_empty()
 }
 
+/// Check if the facet ends with `@` followed by exactly 8 ASCII digits.
+///
+/// Other Unicode digits like Arabic-Indic or fullwidth digits are
+/// not accepted. In contrast to [`has_date_like_suffix()`] the
+/// character that precedes the `@` character is not checked.
+#[must_use]
+pub fn date_suffix_is_ascii_digits(facet: &str) -> bool {
+    let Some(suffix_start) = facet.len().checked_sub(DATE_LIKE_SUFFIX_LEN) else {
+        return false;
+    };
+    let Some((b'@', digits)) = facet.as_bytes()[suffix_start..].split_first() else {
+        return false;
+    };
+    digits.iter().all(u8::is_ascii_digit)
+}
+
 /// Check for a date-like suffix in the facet.
+///
+/// The date-like suffix is anchored at the end of the facet. It consists
+/// of a `@` character followed by exactly 8 ASCII digits. The `@`
+/// character must either be the first character of the facet or must
+/// be preceded by a non-whitespace character. Facets that end with `@`
+/// followed by more than 8 digits like `@123456789` have no date-like
+/// suffix.
 #[must_use]
 pub fn has_date_like_suffix(facet: &str) -> bool {
     debug_assert!(is_valid(facet));
@@ -32,6 +133,10 @@ pub fn has_date_like_suffix(facet: &str) -> bool {
 }
 
 /// Split a facet into a prefix and the date-like suffix.
+///
+/// The suffix must consist of a `@` character followed by exactly 8
+/// ASCII digits. In contrast to [`has_date_like_suffix()`] the prefix
+/// may end with whitespace.
 #[must_use]
 pub fn try_split_into_prefix_and_date_like_suffix(facet: &str) -> Option<(&str, &str)> {
     debug_assert!(is_valid(facet));
@@ -40,7 +145,10 @@ pub fn try_split_into_prefix_and_date_like_suffix(facet: &str) -> Option<(&str,
     }
     let prefix_len = facet.len() - DATE_LIKE_SUFFIX_LEN;
     let date_suffix = &facet[prefix_len..];
-    if !date_suffix.is_ascii() {
+    let Some((b'@', digits)) = date_suffix.as_bytes().split_first() else {
+        return None;
+    };
+    if !digits.iter().all(u8::is_ascii_digit) {
         return None;
     }
     let prefix = &facet[..prefix_len];
@@ -52,16 +160,161 @@ pub fn try_split_into_prefix_and_date_like_suffix(facet: &str) -> Option<(&str,
 pub fn try_split_into_prefix_and_parse_date_suffix(facet: &str) -> Option<(&str, Option<Date>)> {
     debug_assert!(is_valid(facet));
     let (prefix, date_suffix) = try_split_into_prefix_and_date_like_suffix(facet)?;
-    let date = Date::parse(date_suffix, DATE_LIKE_SUFFIX_FORMAT).ok();
+    let date = parse_date_like_suffix(date_suffix);
     (prefix, date).into()
 }
 
-const DATE_LIKE_SUFFIX_FORMAT: &[FormatItem<'static>] = format_description!("@[year][month][day]");
+/// A date-like suffix that does not encode a valid date
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+#[display("invalid date-like suffix {suffix}")]
+pub struct DateParseError {
+    /// The date-like suffix, including the leading `@`.
+    #[error(not(source))]
+    pub suffix: String,
+
+    /// The parse error.
+    pub source: time::error::Parse,
+}
+
+/// Split a facet into a prefix and parse the date suffix, reporting parse errors.
+///
+/// Returns `Ok(None)` if the facet has no date-like suffix and
+/// `Ok(Some(..))` with the prefix and the date if the date-like
+/// suffix encodes a valid date.
+///
+/// # Errors
+///
+/// Returns a [`DateParseError`] if the facet has a date-like suffix
+/// that does not encode a valid date, e.g. `@99999999`.
+pub fn parse_date_suffix(facet: &str) -> Result<Option<(&str, Date)>, DateParseError> {
+    debug_assert!(is_valid(facet));
+    let Some((prefix, date_suffix)) = try_split_into_prefix_and_date_like_suffix(facet) else {
+        return Ok(None);
+    };
+    let date = Date::parse(date_suffix, DATE_SUFFIX_FORMAT).map_err(|source| DateParseError {
+        suffix: date_suffix.to_owned(),
+        source,
+    })?;
+    Ok(Some((prefix, date)))
+}
+
+/// Analyze a facet by splitting it into a prefix and an optional date-like suffix.
+///
+/// The prefix is the whole facet if it has no date-like suffix, i.e.
+/// if [`has_date_like_suffix()`] returns `false`.
+#[must_use]
+pub fn analyze(facet: &str) -> (&str, Option<&str>) {
+    debug_assert!(is_valid(facet));
+    if !has_date_like_suffix(facet) {
+        return (facet, None);
+    }
+    // The regex only matches ASCII characters at the end
+    let (prefix, suffix) = facet.split_at(facet.len() - DATE_LIKE_SUFFIX_LEN);
+    (prefix, Some(suffix))
+}
+
+/// A token of a facet
+///
+/// See also: [`tokenize()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacetToken<'a> {
+    /// A non-empty hierarchy segment.
+    Segment(&'a str),
+
+    /// The separator `/` between hierarchy segments.
+    SegmentSeparator,
+
+    /// The date-like suffix, including the leading `@`.
+    DateSuffix(&'a str),
+}
+
+/// Split a facet into tokens, e.g. for syntax highlighting.
+///
+/// The prefix of the facet is split into segments at each `/`
+/// character. Empty segments are omitted, but all separators are
+/// preserved. The date-like suffix is always the last token, see
+/// [`analyze()`].
+pub fn tokenize(facet: &str) -> impl Iterator<Item = FacetToken<'_>> {
+    let (prefix, date_like_suffix) = analyze(facet);
+    prefix
+        .split('/')
+        .enumerate()
+        .flat_map(|(i, segment)| {
+            (i > 0)
+                .then_some(FacetToken::SegmentSeparator)
+                .into_iter()
+                .chain((!segment.is_empty()).then_some(FacetToken::Segment(segment)))
+        })
+        .chain(date_like_suffix.map(FacetToken::DateSuffix))
+}
+
+/// Count the number of consecutive date-like suffixes in a facet.
+///
+/// Facets with more than one date-like suffix like `text@20220101@20230101`
+/// are valid, but probably the result of accidentally appending a date
+/// suffix to a facet that already has one.
+#[must_use]
+pub fn count_date_like_suffixes(facet: &str) -> usize {
+    let mut count = 0;
+    let mut remainder = facet;
+    while let (prefix, Some(_)) = analyze(remainder) {
+        count += 1;
+        remainder = prefix;
+    }
+    count
+}
+
+/// Strip the date-like suffix from a facet.
+///
+/// Returns the whole facet if it has no date-like suffix.
+#[must_use]
+pub fn prefix_without_date_suffix(facet: &str) -> &str {
+    let (prefix, _) = analyze(facet);
+    prefix
+}
+
+/// The earliest plausible date of a date-like suffix.
+pub const MIN_DATE: Date = date!(1900 - 01 - 01);
+
+/// The latest plausible date of a date-like suffix.
+pub const MAX_DATE: Date = date!(2200 - 12 - 31);
+
+/// Check if the facet has a date-like suffix with a plausible date.
+///
+/// The suffix must encode a valid date between [`MIN_DATE`] and
+/// [`MAX_DATE`] (inclusive). Use [`is_date_suffix_in_range()`] for
+/// a custom range.
+#[must_use]
+pub fn is_plausible_date_suffix(facet: &str) -> bool {
+    is_date_suffix_in_range(facet, MIN_DATE..=MAX_DATE)
+}
+
+/// Check if the facet has a date-like suffix with a date in the given range.
+///
+/// Returns `false` if the facet has no date-like suffix or if the
+/// suffix does not encode a valid date.
+#[must_use]
+pub fn is_date_suffix_in_range(facet: &str, range: RangeInclusive<Date>) -> bool {
+    let (_, date_like_suffix) = analyze(facet);
+    date_like_suffix
+        .and_then(parse_date_like_suffix)
+        .is_some_and(|date| range.contains(&date))
+}
+
+pub(crate) fn parse_date_like_suffix(date_like_suffix: &str) -> Option<Date> {
+    Date::parse(date_like_suffix, DATE_SUFFIX_FORMAT).ok()
+}
+
+/// The format description of date-like suffixes, i.e. `@yyyyMMdd`
+///
+/// See also: [`format_date_suffix()`]
+pub const DATE_SUFFIX_FORMAT: &[FormatItem<'static>] = format_description!("@[year][month][day]");
 
 // @yyyyMMdd
 const DATE_LIKE_SUFFIX_LEN: usize = 1 + 8;
 
-const DATE_LIKE_SUFFIX_REGEX_STR: &str = r"(^|[^\s])@\d{8}$";
+// Only ASCII digits, `\d` would also match other Unicode digits
+const DATE_LIKE_SUFFIX_REGEX_STR: &str = r"(^|[^\s])@[0-9]{8}$";
 
 static DATE_LIKE_SUFFIX_REGEX: OnceLock<Regex> = OnceLock::new();
 
@@ -73,7 +326,7 @@ fn date_like_suffix_regex() -> &'static Regex {
     DATE_LIKE_SUFFIX_REGEX.get_or_init(|| DATE_LIKE_SUFFIX_REGEX_STR.parse().unwrap())
 }
 
-const INVALID_DATE_LIKE_SUFFIX_REGEX_STR: &str = r"[\s]+@\d{8}$";
+const INVALID_DATE_LIKE_SUFFIX_REGEX_STR: &str = r"[\s]+@[0-9]{8}$";
 
 static INVALID_DATE_LIKE_SUFFIX_REGEX: OnceLock<Regex> = OnceLock::new();
 
@@ -91,10 +344,47 @@ pub fn has_invalid_date_like_suffix(facet: &str) -> bool {
     invalid_date_like_suffix_regex().is_match(facet.as_bytes())
 }
 
-fn format_date_like_suffix(date: Date) -> Result<String, time::error::Format> {
-    // Use a `CompactString` for formatting the date-like suffix
-    // to avoid allocating a (probably temporary) `String`.
-    date.format(DATE_LIKE_SUFFIX_FORMAT)
+const MONTH_SUFFIX_FORMAT: &[FormatItem<'static>] = format_description!("@[year][month]");
+
+const DATE_TIME_SECONDS_SUFFIX_FORMAT: &[FormatItem<'static>] =
+    format_description!("@[year][month][day]T[hour][minute][second]");
+
+/// Granularity of a timestamp suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateGranularity {
+    /// Calendar day, formatted as `@yyyyMMdd`
+    ///
+    /// This is the only granularity that results in a date-like suffix.
+    Day,
+
+    /// Calendar month, formatted as `@yyyyMM`
+    Month,
+
+    /// Calendar day and time with seconds precision,
+    /// formatted as `@yyyyMMddTHHmmss`
+    DateTimeSeconds,
+}
+
+impl DateGranularity {
+    const fn suffix_format(self) -> &'static [FormatItem<'static>] {
+        match self {
+            Self::Day => DATE_SUFFIX_FORMAT,
+            Self::Month => MONTH_SUFFIX_FORMAT,
+            Self::DateTimeSeconds => DATE_TIME_SECONDS_SUFFIX_FORMAT,
+        }
+    }
+}
+
+/// Format a date as a date-like suffix, e.g. `@20220625`.
+///
+/// The result is identical to the suffix of facets created by
+/// [`Facet::from_prefix_with_date_suffix()`].
+///
+/// # Errors
+///
+/// Returns an error if formatting of the given `date` fails.
+pub fn format_date_suffix(date: Date) -> Result<String, time::error::Format> {
+    date.format(DATE_SUFFIX_FORMAT)
 }
 
 /// Common trait for facets
@@ -112,6 +402,9 @@ pub trait Facet: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
     }
 
     /// Create a facet from a copy-on-write string.
+    ///
+    /// Implementations should take ownership of an owned string
+    /// without copying it.
     #[must_use]
     fn from_cow_str(facet: Cow<'_, str>) -> Self;
 
@@ -128,7 +421,7 @@ pub trait Facet: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
     ///
     /// Returns an error if formatting of the given `date` fails.
     fn from_prefix_with_date_suffix(prefix: &str, date: Date) -> Result<Self, time::error::Format> {
-        let suffix = format_date_like_suffix(date)?;
+        let suffix = format_date_suffix(date)?;
         Ok(Self::from_format_args(format_args!("{prefix}{suffix}")))
     }
 
@@ -144,12 +437,58 @@ pub trait Facet: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
         prefix_args: fmt::Arguments<'_>,
         date: Date,
     ) -> Result<Self, time::error::Format> {
-        let suffix = format_date_like_suffix(date)?;
+        let suffix = format_date_suffix(date)?;
         Ok(Self::from_format_args(format_args!(
             "{prefix_args}{suffix}"
         )))
     }
 
+    /// Concatenate a prefix and a timestamp suffix to a facet.
+    ///
+    /// The timestamp is formatted according to the requested `granularity`.
+    /// Only [`DateGranularity::Day`] results in a date-like facet, see
+    /// [`has_date_like_suffix()`].
+    ///
+    /// The date and time of `date_time` are used as-is, i.e. in the time
+    /// zone of its offset. Callers are responsible for converting it into
+    /// UTC or local time beforehand.
+    ///
+    /// The prefix string must not end with trailing whitespace,
+    /// otherwise the resulting facet is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if formatting of the given `date_time` fails.
+    fn from_prefix_with_datetime(
+        prefix: &str,
+        date_time: OffsetDateTime,
+        granularity: DateGranularity,
+    ) -> Result<Self, time::error::Format> {
+        let suffix = date_time.format(granularity.suffix_format())?;
+        Ok(Self::from_format_args(format_args!("{prefix}{suffix}")))
+    }
+
+    /// Check if the facet contains the given substring.
+    ///
+    /// Named differently than [`str::contains()`] to avoid shadowing the
+    /// pattern-generic string methods that are accessible through `Deref`.
+    #[must_use]
+    fn contains_str(&self, needle: &str) -> bool {
+        self.as_ref().contains(needle)
+    }
+
+    /// Check if the facet starts with the given prefix.
+    #[must_use]
+    fn starts_with_str(&self, prefix: &str) -> bool {
+        self.as_ref().starts_with(prefix)
+    }
+
+    /// Check if the facet ends with the given suffix.
+    #[must_use]
+    fn ends_with_str(&self, suffix: &str) -> bool {
+        self.as_ref().ends_with(suffix)
+    }
+
     /// [`is_valid()`]
     #[must_use]
     fn is_valid(&self) -> bool {
@@ -179,6 +518,75 @@ pub trait Facet: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
     fn try_split_into_prefix_and_parse_date_suffix(&self) -> Option<(&str, Option<Date>)> {
         try_split_into_prefix_and_parse_date_suffix(self.as_ref())
     }
+
+    /// [`analyze()`]
+    #[must_use]
+    fn analyze(&self) -> (&str, Option<&str>) {
+        analyze(self.as_ref())
+    }
+
+    /// Check if the facet consists only of a date-like suffix.
+    ///
+    /// Returns `true` for facets like `@20220625` with an empty prefix.
+    #[must_use]
+    fn is_date_only(&self) -> bool {
+        matches!(self.analyze(), ("", Some(_)))
+    }
+
+    /// [`count_date_like_suffixes()`]
+    #[must_use]
+    fn count_date_like_suffixes(&self) -> usize {
+        count_date_like_suffixes(self.as_ref())
+    }
+
+    /// [`prefix_without_date_suffix()`]
+    #[must_use]
+    fn prefix_without_date_suffix(&self) -> &str {
+        prefix_without_date_suffix(self.as_ref())
+    }
+
+    /// Append a hierarchy level.
+    ///
+    /// Appends `/` followed by `segment` to a non-empty facet or
+    /// returns just `segment` if the facet is empty. The segment is
+    /// inserted before a date-like suffix, i.e. joining `wishlist@20220625`
+    /// with `summer` results in `wishlist/summer@20220625`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FacetInvalidity::InvalidSegment`] if `segment` is empty,
+    /// contains a slash `/` or whitespace, or has a date-like suffix.
+    /// Returns other [`FacetInvalidity`]s if the resulting facet is invalid.
+    fn join_segment(&self, segment: &str) -> Result<Self, FacetInvalidity> {
+        if segment.is_empty()
+            || segment.contains(|c: char| c == '/' || c.is_whitespace())
+            || has_date_like_suffix(segment)
+        {
+            return Err(FacetInvalidity::InvalidSegment);
+        }
+        let (prefix, date_like_suffix) = self.analyze();
+        let date_like_suffix = date_like_suffix.unwrap_or_default();
+        let joined = if prefix.is_empty() {
+            Self::from_format_args(format_args!("{segment}{date_like_suffix}"))
+        } else {
+            Self::from_format_args(format_args!("{prefix}/{segment}{date_like_suffix}"))
+        };
+        validate(joined.as_ref())?;
+        Ok(joined)
+    }
+
+    /// Check if two facets are equal after stripping their date-like suffixes.
+    #[must_use]
+    fn eq_ignoring_date(&self, other: &Self) -> bool {
+        self.prefix_without_date_suffix() == other.prefix_without_date_suffix()
+    }
+
+    /// Compare two facets after stripping their date-like suffixes.
+    #[must_use]
+    fn cmp_ignoring_date(&self, other: &Self) -> Ordering {
+        self.prefix_without_date_suffix()
+            .cmp(other.prefix_without_date_suffix())
+    }
 }
 
 /// Facet with a [`CompactString`] representation
@@ -3,9 +3,11 @@
 
 #![allow(clippy::redundant_clone)]
 
+use std::cmp::Ordering;
+
 use time::Date;
 
-use super::{CompactFacet as Facet, Facet as _};
+use super::{CompactFacet as Facet, DateGranularity, Facet as _, FacetInvalidity};
 
 #[test]
 fn try_split_into_prefix_and_date_like_suffix_should_accept_and_preserve_invalid_whitespace() {
@@ -61,3 +63,336 @@ fn has_date_like_suffix() {
     assert!(!super::has_date_like_suffix("a-20220625"));
     assert!(!super::has_date_like_suffix("a20220625"));
 }
+
+#[test]
+fn prefix_without_date_suffix() {
+    assert_eq!("", super::prefix_without_date_suffix("@20220625"));
+    assert_eq!(
+        "wishlist",
+        super::prefix_without_date_suffix("wishlist@20220625")
+    );
+    assert_eq!("wishlist", super::prefix_without_date_suffix("wishlist"));
+    assert_eq!(
+        "wishlist @20220625",
+        super::prefix_without_date_suffix("wishlist @20220625")
+    );
+    assert_eq!(
+        "wishlist-20220625",
+        super::prefix_without_date_suffix("wishlist-20220625")
+    );
+}
+
+#[test]
+fn eq_and_cmp_ignoring_date() {
+    let dated_2022 = Facet::from_str("wishlist@20220101");
+    let dated_2023 = Facet::from_str("wishlist@20230101");
+    let undated = Facet::from_str("wishlist");
+    let other = Facet::from_str("played@20220101");
+    let date_only = Facet::from_str("@20220101");
+    assert!(dated_2022.eq_ignoring_date(&dated_2023));
+    assert!(dated_2022.eq_ignoring_date(&undated));
+    assert!(!dated_2022.eq_ignoring_date(&other));
+    assert!(!dated_2022.eq_ignoring_date(&date_only));
+    assert!(date_only.eq_ignoring_date(&Facet::default()));
+    assert_eq!(Ordering::Equal, dated_2022.cmp_ignoring_date(&dated_2023));
+    assert_eq!(Ordering::Equal, undated.cmp_ignoring_date(&dated_2023));
+    assert_eq!(Ordering::Greater, dated_2022.cmp_ignoring_date(&other));
+    assert_eq!(Ordering::Less, date_only.cmp_ignoring_date(&other));
+}
+
+#[test]
+fn from_prefix_with_datetime() {
+    let date_time = time::macros::datetime!(2022-06-25 23:59:58 +02:00);
+    let facet =
+        Facet::from_prefix_with_datetime("played", date_time, DateGranularity::Day).unwrap();
+    assert_eq!("played@20220625", facet.as_ref());
+    assert!(facet.has_date_like_suffix());
+    let facet =
+        Facet::from_prefix_with_datetime("played", date_time, DateGranularity::Month).unwrap();
+    assert_eq!("played@202206", facet.as_ref());
+    assert!(!facet.has_date_like_suffix());
+    let facet =
+        Facet::from_prefix_with_datetime("", date_time, DateGranularity::DateTimeSeconds).unwrap();
+    assert_eq!("@20220625T235958", facet.as_ref());
+    assert!(!facet.has_date_like_suffix());
+    // The offset of the timestamp is used as-is
+    let facet = Facet::from_prefix_with_datetime(
+        "played",
+        date_time.to_offset(time::UtcOffset::UTC),
+        DateGranularity::Day,
+    )
+    .unwrap();
+    assert_eq!("played@20220625", facet.as_ref());
+    let facet = Facet::from_prefix_with_datetime(
+        "played",
+        date_time.to_offset(time::macros::offset!(+03:00)),
+        DateGranularity::Day,
+    )
+    .unwrap();
+    assert_eq!("played@20220626", facet.as_ref());
+}
+
+#[test]
+fn analyze() {
+    assert_eq!(("", Some("@20220625")), super::analyze("@20220625"));
+    assert_eq!(("a/b", Some("@20220625")), super::analyze("a/b@20220625"));
+    assert_eq!(("a/b", None), super::analyze("a/b"));
+    assert_eq!(("a @20220625", None), super::analyze("a @20220625"));
+    assert_eq!(("", None), super::analyze(""));
+}
+
+#[test]
+fn count_date_like_suffixes() {
+    assert_eq!(0, super::count_date_like_suffixes(""));
+    assert_eq!(0, super::count_date_like_suffixes("text"));
+    assert_eq!(1, super::count_date_like_suffixes("@20230101"));
+    assert_eq!(1, super::count_date_like_suffixes("text@20230101"));
+    assert_eq!(
+        1,
+        super::count_date_like_suffixes("text @20220101@20230101")
+    );
+    assert_eq!(2, super::count_date_like_suffixes("text@20220101@20230101"));
+    assert_eq!(2, super::count_date_like_suffixes("@20220101@20230101"));
+    assert_eq!(
+        3,
+        super::count_date_like_suffixes("a@20210101@20220101@20230101")
+    );
+}
+
+#[test]
+fn join_segment() {
+    let facet = Facet::from_str("");
+    let facet = facet.join_segment("wishlist").unwrap();
+    assert_eq!("wishlist", facet.as_ref());
+    let facet = facet.join_segment("Summer2022").unwrap();
+    assert_eq!("wishlist/Summer2022", facet.as_ref());
+    for segment in [
+        "",
+        "a/b",
+        "/a",
+        " a",
+        "a\t",
+        "Summer 2022",
+        "a\u{a0}b",
+        "played@20220625",
+        "played @20220625",
+        "@20220625",
+    ] {
+        assert_eq!(
+            Err(FacetInvalidity::InvalidSegment),
+            facet.join_segment(segment)
+        );
+    }
+
+    // The date-like suffix is preserved
+    let facet = Facet::from_str("wishlist@20220625");
+    assert_eq!(
+        "wishlist/summer@20220625",
+        facet.join_segment("summer").unwrap().as_ref()
+    );
+    let facet = Facet::from_str("@20220625");
+    assert_eq!(
+        "summer@20220625",
+        facet.join_segment("summer").unwrap().as_ref()
+    );
+    let facet = Facet::from_str("wishlist@20230101");
+    assert_eq!(
+        Err(FacetInvalidity::InvalidSegment),
+        facet.join_segment("x@20220101")
+    );
+}
+
+#[test]
+fn at_sign_followed_by_more_than_8_digits_is_not_a_date_like_suffix() {
+    for facet in [
+        "@123456789",
+        "user@123456789",
+        "a@b@123456789",
+        "x1@2345678",
+    ] {
+        let facet = Facet::from_str(facet);
+        assert!(!facet.has_date_like_suffix());
+        assert!(!super::has_invalid_date_like_suffix(facet.as_ref()));
+        assert!(facet.try_split_into_prefix_and_date_like_suffix().is_none());
+        assert_eq!((facet.as_ref(), None), facet.analyze());
+    }
+    let facet = Facet::from_str("user@example@12345678");
+    assert_eq!(("user@example", Some("@12345678")), facet.analyze());
+    assert_eq!(
+        Some(("user@example", "@12345678")),
+        facet.try_split_into_prefix_and_date_like_suffix()
+    );
+}
+
+#[test]
+fn owned_input_is_moved_without_reallocation() {
+    use std::borrow::Cow;
+
+    use super::StdFacet;
+
+    let heap_allocated = || "a facet that does not fit into an inline string".to_owned();
+
+    let facet = heap_allocated();
+    let ptr = facet.as_ptr();
+    assert_eq!(ptr, StdFacet::from_string(facet).as_ptr());
+    let facet = heap_allocated();
+    let ptr = facet.as_ptr();
+    assert_eq!(ptr, StdFacet::from_cow_str(Cow::Owned(facet)).as_ptr());
+
+    let facet = heap_allocated();
+    let ptr = facet.as_ptr();
+    assert_eq!(ptr, Facet::from_string(facet).as_ptr());
+    let facet = heap_allocated();
+    let ptr = facet.as_ptr();
+    assert_eq!(ptr, Facet::from_cow_str(Cow::Owned(facet)).as_ptr());
+}
+
+#[test]
+fn is_date_only() {
+    assert!(Facet::from_str("@20220625").is_date_only());
+    assert!(Facet::from_str("@99999999").is_date_only());
+    assert!(!Facet::from_str("x@20220625").is_date_only());
+    assert!(!Facet::from_str("@@20220625").is_date_only());
+    assert!(!Facet::from_str("").is_date_only());
+    assert!(!Facet::from_str("@2022062").is_date_only());
+}
+
+#[test]
+fn substring_matching() {
+    let facet = Facet::from_str("wishlist/summer@20220625");
+    assert!(facet.contains_str("summer"));
+    assert!(facet.starts_with_str("wishlist/"));
+    assert!(facet.ends_with_str("@20220625"));
+    assert!(!facet.contains_str("Summer"));
+    assert!(!facet.starts_with_str("summer"));
+    assert!(!facet.ends_with_str("summer"));
+    // The pattern-generic string methods are not shadowed
+    assert!(facet.contains('@'));
+    assert!(facet.starts_with(char::is_alphabetic));
+}
+
+#[test]
+fn tokenize() {
+    use super::FacetToken::{DateSuffix, Segment, SegmentSeparator};
+
+    let tokenize = |facet| super::tokenize(facet).collect::<Vec<_>>();
+    assert!(tokenize("").is_empty());
+    assert_eq!(vec![DateSuffix("@20220625")], tokenize("@20220625"));
+    assert_eq!(vec![Segment("spotify")], tokenize("spotify"));
+    assert_eq!(
+        vec![
+            Segment("wishlist"),
+            SegmentSeparator,
+            Segment("summer"),
+            DateSuffix("@20220625")
+        ],
+        tokenize("wishlist/summer@20220625")
+    );
+    assert_eq!(
+        vec![
+            Segment("a"),
+            SegmentSeparator,
+            SegmentSeparator,
+            Segment("b"),
+            SegmentSeparator,
+            DateSuffix("@20220625")
+        ],
+        tokenize("a//b/@20220625")
+    );
+}
+
+#[test]
+fn plausible_date_suffix() {
+    for facet in ["@20220625", "played@19000101", "x@22001231"] {
+        assert!(super::is_plausible_date_suffix(facet), "{facet}");
+    }
+    for facet in [
+        "",
+        "played",
+        "@00000000",
+        "@99999999",
+        "@20220230",
+        "@18991231",
+        "@22010101",
+    ] {
+        assert!(!super::is_plausible_date_suffix(facet), "{facet}");
+    }
+    let year_2022 = Date::from_calendar_date(2022, time::Month::January, 1).unwrap()
+        ..=Date::from_calendar_date(2022, time::Month::December, 31).unwrap();
+    assert!(super::is_date_suffix_in_range(
+        "@20220625",
+        year_2022.clone()
+    ));
+    assert!(!super::is_date_suffix_in_range("@20230101", year_2022));
+}
+
+#[test]
+fn parse_date_suffix_should_distinguish_missing_and_invalid_dates() {
+    assert_eq!(Ok(None), super::parse_date_suffix(""));
+    assert_eq!(Ok(None), super::parse_date_suffix("facet"));
+    assert_eq!(
+        Ok(Some((
+            "facet",
+            Date::from_calendar_date(2022, time::Month::June, 25).unwrap()
+        ))),
+        super::parse_date_suffix("facet@20220625")
+    );
+    let err = super::parse_date_suffix("facet@99999999").unwrap_err();
+    assert_eq!("@99999999", err.suffix);
+    assert_eq!("invalid date-like suffix @99999999", err.to_string());
+    assert!(std::error::Error::source(&err).is_some());
+    assert!(super::parse_date_suffix("@20220230").is_err());
+}
+
+#[test]
+fn coerce() {
+    for (input, expected) in [
+        ("", ""),
+        ("  ", ""),
+        ("/", ""),
+        (" / /facet ", "facet"),
+        ("a/b", "a/b"),
+        ("played @20220625", "played@20220625"),
+        (" / played \t@20220625 ", "played@20220625"),
+        ("@20220625", "@20220625"),
+        ("/ @20220625", "@20220625"),
+    ] {
+        let coerced = super::coerce(input);
+        assert_eq!(expected, coerced);
+        assert!(super::validate(&coerced).is_ok());
+    }
+}
+
+#[test]
+fn format_date_suffix() {
+    let date = Date::from_calendar_date(2022, time::Month::June, 5).unwrap();
+    let suffix = super::format_date_suffix(date).unwrap();
+    assert_eq!("@20220605", suffix);
+    assert_eq!(
+        format!("played{suffix}"),
+        Facet::from_prefix_with_date_suffix("played", date)
+            .unwrap()
+            .as_ref()
+    );
+    assert_eq!(Ok(date), Date::parse(&suffix, super::DATE_SUFFIX_FORMAT));
+}
+
+#[test]
+fn non_ascii_digits_are_not_a_date_like_suffix() {
+    // Arabic-Indic digits and fullwidth digits
+    for facet in [
+        "@\u{662}\u{660}\u{662}\u{662}\u{660}\u{666}\u{662}\u{665}",
+        "played@\u{ff12}\u{ff10}\u{ff12}\u{ff12}\u{ff10}\u{ff16}\u{ff12}\u{ff15}",
+        "played @\u{ff12}\u{ff10}\u{ff12}\u{ff12}\u{ff10}\u{ff16}\u{ff12}\u{ff15}",
+        "@2022062\u{665}",
+    ] {
+        assert!(!super::has_date_like_suffix(facet), "{facet}");
+        assert!(!super::has_invalid_date_like_suffix(facet), "{facet}");
+        assert!(!super::date_suffix_is_ascii_digits(facet), "{facet}");
+        assert_eq!((facet, None), super::analyze(facet));
+        assert!(super::validate(facet).is_ok(), "{facet}");
+    }
+    assert!(super::date_suffix_is_ascii_digits("played@20220625"));
+    assert!(super::date_suffix_is_ascii_digits("@99999999"));
+    assert!(!super::date_suffix_is_ascii_digits("played"));
+}
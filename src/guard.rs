@@ -0,0 +1,166 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+//! Guarded mutable access to tag components
+
+use std::ops::{Deref, DerefMut};
+
+use crate::{Facet, Label, Name, Property, Tag, TagInvalidity};
+
+/// Mutable access to the facet of a tag
+///
+/// The validity of the tag is checked when the guard is dropped
+/// in debug builds. Use [`FacetGuard::commit()`] for checking the
+/// validity explicitly.
+///
+/// See also: [`Tag::facet_mut()`]
+#[derive(Debug)]
+pub struct FacetGuard<'a, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    tag: &'a mut Tag<F, L, N, V>,
+    committed: bool,
+}
+
+impl<'a, F, L, N, V> FacetGuard<'a, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    pub(crate) fn new(tag: &'a mut Tag<F, L, N, V>) -> Self {
+        Self {
+            tag,
+            committed: false,
+        }
+    }
+
+    /// Finish editing and validate the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the edited tag is invalid.
+    pub fn commit(mut self) -> Result<(), TagInvalidity> {
+        self.committed = true;
+        self.tag.validate()
+    }
+}
+
+impl<F, L, N, V> Deref for FacetGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    type Target = F;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tag.facet
+    }
+}
+
+impl<F, L, N, V> DerefMut for FacetGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tag.facet
+    }
+}
+
+impl<F, L, N, V> Drop for FacetGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    fn drop(&mut self) {
+        if !self.committed && !std::thread::panicking() {
+            self.tag.debug_assert_valid();
+        }
+    }
+}
+
+/// Mutable access to the properties of a tag
+///
+/// The validity of the tag is checked when the guard is dropped
+/// in debug builds. Use [`PropsGuard::commit()`] for checking the
+/// validity explicitly.
+///
+/// See also: [`Tag::props_mut()`]
+#[derive(Debug)]
+pub struct PropsGuard<'a, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    tag: &'a mut Tag<F, L, N, V>,
+    committed: bool,
+}
+
+impl<'a, F, L, N, V> PropsGuard<'a, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    pub(crate) fn new(tag: &'a mut Tag<F, L, N, V>) -> Self {
+        Self {
+            tag,
+            committed: false,
+        }
+    }
+
+    /// Finish editing and validate the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the edited tag is invalid.
+    pub fn commit(mut self) -> Result<(), TagInvalidity> {
+        self.committed = true;
+        self.tag.validate()
+    }
+}
+
+impl<F, L, N, V> Deref for PropsGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    type Target = Vec<Property<N, V>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tag.props
+    }
+}
+
+impl<F, L, N, V> DerefMut for PropsGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tag.props
+    }
+}
+
+impl<F, L, N, V> Drop for PropsGuard<'_, F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    fn drop(&mut self) {
+        if !self.committed && !std::thread::panicking() {
+            self.tag.debug_assert_valid();
+        }
+    }
+}
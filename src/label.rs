@@ -6,13 +6,58 @@
 use std::{borrow::Cow, fmt, ops::Deref};
 
 use compact_str::{CompactString, ToCompactString as _};
+use derive_more::{Display, Error};
+
+/// Reasons for an invalid label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+#[allow(clippy::module_name_repetitions)]
+pub enum LabelInvalidity {
+    /// Leading or trailing whitespace.
+    #[display("leading or trailing whitespace")]
+    LeadingOrTrailingWhitespace,
+
+    /// Leading slash `/`.
+    #[display("leading slash")]
+    LeadingSlash,
+}
+
+/// Validate the given label.
+///
+/// An empty label is valid.
+///
+/// # Errors
+///
+/// Returns a [`LabelInvalidity`] if the label is invalid.
+pub fn validate(label: &str) -> Result<(), LabelInvalidity> {
+    if label.trim() != label {
+        return Err(LabelInvalidity::LeadingOrTrailingWhitespace);
+    }
+    if label.as_bytes().first() == Some(&b'/') {
+        return Err(LabelInvalidity::LeadingSlash);
+    }
+    Ok(())
+}
+
+/// Coerce arbitrary input into a valid label.
+///
+/// Trims leading/trailing whitespace and strips leading slashes `/`.
+/// The result is always valid, but might be empty.
+#[must_use]
+pub fn coerce(input: &str) -> String {
+    let mut label = input.trim();
+    while let Some(stripped) = label.strip_prefix('/') {
+        label = stripped.trim_start();
+    }
+    debug_assert!(is_valid(label));
+    label.to_owned()
+}
 
 /// Check if the given label is valid.
 ///
 /// An empty label is valid.
 #[must_use]
 pub fn is_valid(label: &str) -> bool {
-    label.trim() == label && label.as_bytes().first() != Some(&b'/')
+    validate(label).is_ok()
 }
 
 /// Check if the given label is empty.
@@ -43,6 +88,9 @@ pub trait Label: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
     /// Create a label from a copy-on-write string.
     ///
     /// The argument must be a valid label.
+    ///
+    /// Implementations should take ownership of an owned string
+    /// without copying it.
     #[must_use]
     fn from_cow_str(label: Cow<'_, str>) -> Self;
 
@@ -50,6 +98,27 @@ pub trait Label: AsRef<str> + fmt::Debug + Default + PartialEq + Ord + Sized {
     #[must_use]
     fn from_format_args(format_args: fmt::Arguments<'_>) -> Self;
 
+    /// Check if the label contains the given substring.
+    ///
+    /// Named differently than [`str::contains()`] to avoid shadowing the
+    /// pattern-generic string methods that are accessible through `Deref`.
+    #[must_use]
+    fn contains_str(&self, needle: &str) -> bool {
+        self.as_ref().contains(needle)
+    }
+
+    /// Check if the label starts with the given prefix.
+    #[must_use]
+    fn starts_with_str(&self, prefix: &str) -> bool {
+        self.as_ref().starts_with(prefix)
+    }
+
+    /// Check if the label ends with the given suffix.
+    #[must_use]
+    fn ends_with_str(&self, suffix: &str) -> bool {
+        self.as_ref().ends_with(suffix)
+    }
+
     /// [`is_valid()`]
     #[must_use]
     fn is_valid(&self) -> bool {
@@ -7,25 +7,57 @@
 
 pub mod docs;
 
-use std::{cmp::Ordering, fmt, str::FromStr, sync::OnceLock};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    fmt, io,
+    ops::Range,
+    str::FromStr,
+    sync::OnceLock,
+};
 
 use anyhow::anyhow;
-use compact_str::format_compact;
 use derive_more::{Display, Error};
+use itertools::Itertools as _;
 use percent_encoding::{percent_decode, percent_encode};
+use time::Date;
 use url::Url;
 
+#[cfg(feature = "binary")]
+pub mod binary;
+
+pub mod borrowed;
+pub use self::borrowed::{DecodedTagsRef, TagRef};
+
+pub mod encoding;
+
+pub mod guard;
+
+#[cfg(feature = "unicode-normalization")]
+pub mod normalization;
+pub use self::guard::{FacetGuard, PropsGuard};
+
 pub mod facet;
-pub use self::facet::{CompactFacet, Facet, StdFacet};
+pub use self::facet::{
+    CompactFacet, DateGranularity, DateParseError, Facet, FacetInvalidity, FacetToken, StdFacet,
+};
 
 pub mod label;
-pub use self::label::{CompactLabel, Label, StdLabel};
+pub use self::label::{CompactLabel, Label, LabelInvalidity, StdLabel};
 
 pub mod props;
-pub use self::props::{CompactName, CompactProperty, Name, Property, StdName, Value};
+pub use self::props::{
+    BorrowedProperty, CompactName, CompactProperty, Name, PropSeparator, Property, StdName, Value,
+};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 /// A tag
+///
+/// The [`Default`] tag is empty and thus **invalid**, see [`Tag::empty()`].
+///
+/// Tags are ordered canonically, see [`Tag::cmp_canonical()`].
 pub struct Tag<F, L, N, V> {
     /// The label
     pub label: L,
@@ -43,6 +75,20 @@ where
     L: Label,
     N: Name,
 {
+    /// Create an empty tag.
+    ///
+    /// The empty tag is **invalid**, because it has neither a label nor a
+    /// facet. It is only supposed to be used as a starting point for
+    /// populating the components. Same as [`Default::default()`].
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            label: L::default(),
+            facet: F::default(),
+            props: Vec::new(),
+        }
+    }
+
     /// Check for a non-empty label.
     #[must_use]
     pub fn has_label(&self) -> bool {
@@ -84,43 +130,435 @@ where
         &self.props
     }
 
+    /// Return the value of the first property with the given name.
+    ///
+    /// Names are matched exactly.
+    #[must_use]
+    pub fn get_prop(&self, name: &str) -> Option<&V> {
+        self.props()
+            .iter()
+            .find(|prop| prop.name().as_ref() == name)
+            .map(Property::value)
+    }
+
+    /// Iterate over all properties with a name that starts with the given prefix.
+    ///
+    /// Supports namespaced property names like `audio.bpm` and
+    /// `audio.key`, e.g. with the prefix `audio.`. Prefixes are matched
+    /// case-sensitively. The properties are yielded in order.
+    pub fn props_with_name_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = &'a Property<N, V>> {
+        self.props()
+            .iter()
+            .filter(move |prop| prop.name().as_ref().starts_with(prefix))
+    }
+
+    /// Return the value of the first property with the given name
+    /// or a default value.
+    ///
+    /// See also: [`Self::get_prop()`]
+    #[must_use]
+    pub fn prop_value_or<'a>(&'a self, name: &str, default: &'a V) -> &'a V {
+        self.get_prop(name).unwrap_or(default)
+    }
+
+    /// Return a copy of the value of the first property with the given
+    /// name or the default value.
+    ///
+    /// See also: [`Self::get_prop()`]
+    #[must_use]
+    pub fn prop_value_or_default(&self, name: &str) -> V
+    where
+        V: Clone + Default,
+    {
+        self.get_prop(name).cloned().unwrap_or_default()
+    }
+
+    /// Check for a property with the given name.
+    ///
+    /// Names are matched exactly.
+    #[must_use]
+    pub fn has_prop(&self, name: &str) -> bool {
+        self.get_prop(name).is_some()
+    }
+
+    /// Return the value of the first property with the given name,
+    /// ignoring ASCII case.
+    ///
+    /// Property names are case-sensitive. This is only a lookup
+    /// convenience for properties from heterogeneous sources.
+    #[must_use]
+    pub fn get_prop_ignore_ascii_case(&self, name: &str) -> Option<&V> {
+        self.props()
+            .iter()
+            .find(|prop| prop.name().as_ref().eq_ignore_ascii_case(name))
+            .map(Property::value)
+    }
+
+    /// Check for a property with the given name, ignoring ASCII case.
+    ///
+    /// See also: [`Self::get_prop_ignore_ascii_case()`]
+    #[must_use]
+    pub fn has_prop_ignore_ascii_case(&self, name: &str) -> bool {
+        self.get_prop_ignore_ascii_case(name).is_some()
+    }
+
     /// Check if the tag is valid.
     #[must_use]
     pub fn is_valid(&self) -> bool {
         self.has_label()
             || (self.has_facet() && (self.has_props() || self.facet().has_date_like_suffix()))
     }
-}
 
-mod encoding {
-    use percent_encoding::{AsciiSet, CONTROLS};
+    /// Validate the tag and all its components.
+    ///
+    /// In contrast to [`Self::is_valid()`] the contents of all
+    /// components are validated thoroughly.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the tag is invalid.
+    pub fn validate(&self) -> Result<(), TagInvalidity> {
+        label::validate(self.label.as_ref()).map_err(TagInvalidity::Label)?;
+        facet::validate(self.facet.as_ref()).map_err(TagInvalidity::Facet)?;
+        if !self.props.iter().all(Property::is_valid) {
+            return Err(TagInvalidity::PropName);
+        }
+        self.validate_components_present()
+    }
+
+    fn validate_components_present(&self) -> Result<(), TagInvalidity> {
+        if self.has_label() {
+            return Ok(());
+        }
+        if !self.has_facet() {
+            return Err(TagInvalidity::MissingLabelAndFacet);
+        }
+        if !self.has_props() && !self.facet().has_date_like_suffix() {
+            return Err(TagInvalidity::FacetOnlyWithoutDate);
+        }
+        Ok(())
+    }
+
+    /// Assert that the tag is valid in debug builds.
+    ///
+    /// Invoked by all mutating functions that preserve the validity
+    /// of a tag. Does nothing in release builds.
+    #[track_caller]
+    pub fn debug_assert_valid(&self) {
+        debug_assert_eq!(Ok(()), self.validate());
+    }
+
+    /// Edit the facet in place.
+    ///
+    /// The validity of the tag is checked after editing, see [`FacetGuard`].
+    pub fn facet_mut(&mut self) -> FacetGuard<'_, F, L, N, V> {
+        FacetGuard::new(self)
+    }
+
+    /// Edit the properties in place.
+    ///
+    /// The validity of the tag is checked after editing, see [`PropsGuard`].
+    pub fn props_mut(&mut self) -> PropsGuard<'_, F, L, N, V> {
+        PropsGuard::new(self)
+    }
+
+    /// Replace the label.
+    ///
+    /// The tag remains unmodified if the new label is invalid or if
+    /// the tag would become invalid without its current label.
+    ///
+    /// Returns the previous label.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the tag would become invalid.
+    pub fn set_label(&mut self, label: L) -> Result<L, TagInvalidity> {
+        label::validate(label.as_ref()).map_err(TagInvalidity::Label)?;
+        let old_label = std::mem::replace(&mut self.label, label);
+        if let Err(err) = self.validate_components_present() {
+            self.label = old_label;
+            return Err(err);
+        }
+        self.debug_assert_valid();
+        Ok(old_label)
+    }
+
+    /// Replace the facet.
+    ///
+    /// The tag remains unmodified if the new facet is invalid or if
+    /// the tag would become invalid with the new facet.
+    ///
+    /// Returns the previous facet.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the tag would become invalid.
+    pub fn set_facet(&mut self, facet: F) -> Result<F, TagInvalidity> {
+        facet::validate(facet.as_ref()).map_err(TagInvalidity::Facet)?;
+        let old_facet = std::mem::replace(&mut self.facet, facet);
+        if let Err(err) = self.validate_components_present() {
+            self.facet = old_facet;
+            return Err(err);
+        }
+        self.debug_assert_valid();
+        Ok(old_facet)
+    }
+
+    /// Stamp a date onto the facet.
+    ///
+    /// Replaces an existing date-like suffix of the facet or appends a
+    /// new date-like suffix otherwise. If the facet prefix without the
+    /// date-like suffix is empty then `default_prefix` is used as the
+    /// prefix, e.g. `Some("dated")` results in `dated@yyyyMMdd`. With
+    /// `None` a date-only facet `@yyyyMMdd` is created.
+    ///
+    /// The tag remains unmodified if the resulting facet is invalid.
+    ///
+    /// Returns the previous facet.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the date could not be formatted
+    /// as a date-like suffix or if the resulting facet is invalid.
+    pub fn set_date_suffix(
+        &mut self,
+        date: Date,
+        default_prefix: Option<&str>,
+    ) -> Result<F, TagInvalidity> {
+        let invalid_date_like_suffix =
+            || TagInvalidity::Facet(FacetInvalidity::InvalidDateLikeSuffix);
+        let mut prefix = self.facet().prefix_without_date_suffix();
+        if prefix.is_empty() {
+            prefix = default_prefix.unwrap_or_default();
+        }
+        let facet = F::from_prefix_with_date_suffix(prefix, date)
+            .map_err(|_| invalid_date_like_suffix())?;
+        // Dates with years outside of 0000..=9999 result in an invalid suffix
+        if !facet::is_valid(facet.as_ref()) || !facet.has_date_like_suffix() {
+            return Err(invalid_date_like_suffix());
+        }
+        self.set_facet(facet)
+    }
+
+    /// Remove all properties.
+    ///
+    /// The tag remains unmodified if it would become invalid
+    /// without properties.
+    ///
+    /// Returns the removed properties.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TagInvalidity`] if the tag would become invalid.
+    pub fn strip_props(&mut self) -> Result<Vec<Property<N, V>>, TagInvalidity> {
+        let old_props = std::mem::take(&mut self.props);
+        if let Err(err) = self.validate_components_present() {
+            self.props = old_props;
+            return Err(err);
+        }
+        self.debug_assert_valid();
+        Ok(old_props)
+    }
+
+    /// Check that no property name occurs more than once.
+    ///
+    /// Duplicate property names are permitted by the format. This
+    /// check is only needed for application-specific constraints.
+    #[must_use]
+    pub fn prop_names_are_unique(&self) -> bool {
+        self.props()
+            .iter()
+            .map(|prop| prop.name().as_ref())
+            .all_unique()
+    }
+
+    /// Resolve properties with duplicate names.
+    ///
+    /// Properties with the same name are considered as duplicates even
+    /// if their values differ. Exact duplicates with the same name and
+    /// value are resolved in the same way. The relative order of the
+    /// remaining properties is preserved.
+    ///
+    /// The tag remains unmodified if `policy` is [`DuplicatePolicy::Reject`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DuplicatePropName`] error with the first duplicate name
+    /// if `policy` is [`DuplicatePolicy::Reject`].
+    pub fn enforce_unique_prop_names(
+        &mut self,
+        policy: DuplicatePolicy,
+    ) -> Result<(), DuplicatePropName> {
+        if policy == DuplicatePolicy::Reject {
+            if let Some(name) = self
+                .props()
+                .iter()
+                .map(|prop| prop.name().as_ref())
+                .duplicates()
+                .next()
+            {
+                return Err(DuplicatePropName {
+                    name: name.to_owned(),
+                });
+            }
+            return Ok(());
+        }
+        let keep = (0..self.props.len())
+            .map(|index| {
+                let name = self.props[index].name().as_ref();
+                let is_same_name = |prop: &Property<N, V>| prop.name().as_ref() == name;
+                if policy == DuplicatePolicy::KeepFirst {
+                    !self.props[..index].iter().any(is_same_name)
+                } else {
+                    !self.props[index + 1..].iter().any(is_same_name)
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut keep = keep.into_iter();
+        self.props.retain(|_| keep.next().unwrap_or(true));
+        self.debug_assert_valid();
+        Ok(())
+    }
+
+    /// Replace the label, consuming the tag.
+    ///
+    /// Only the new label is validated. The resulting tag might still
+    /// be incomplete, e.g. when building a tag from [`Self::empty()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LabelInvalidity`] if the label is invalid.
+    pub fn with_label(mut self, label: L) -> Result<Self, LabelInvalidity> {
+        label::validate(label.as_ref())?;
+        self.label = label;
+        Ok(self)
+    }
 
-    const CONTROLS_ESCAPE: &AsciiSet = &CONTROLS.add(b'%');
+    /// Replace the facet, consuming the tag.
+    ///
+    /// Only the new facet is validated. The resulting tag might still
+    /// be incomplete, e.g. when building a tag from [`Self::empty()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FacetInvalidity`] if the facet is invalid.
+    pub fn with_facet(mut self, facet: F) -> Result<Self, FacetInvalidity> {
+        facet::validate(facet.as_ref())?;
+        self.facet = facet;
+        Ok(self)
+    }
 
-    /// <https://url.spec.whatwg.org/#fragment-percent-encode-set>
-    const FRAGMENT: &AsciiSet = &CONTROLS_ESCAPE
-        .add(b' ')
-        .add(b'"')
-        .add(b'<')
-        .add(b'>')
-        .add(b'`');
+    /// Append a property, consuming the tag.
+    #[must_use]
+    pub fn with_prop(mut self, name: N, value: V) -> Self {
+        self.props.push(Property { name, value });
+        self
+    }
 
-    pub(super) const LABEL: &AsciiSet = FRAGMENT;
+    /// Return the facet prefix and the date of the facet.
+    ///
+    /// The prefix is the facet without its date-like suffix or the whole
+    /// facet if it has no date-like suffix. The date is `None` if the facet
+    /// has no date-like suffix or if the suffix does not encode a valid date.
+    #[must_use]
+    pub fn facet_prefix_and_date(&self) -> (&str, Option<Date>) {
+        let (prefix, date_like_suffix) = self.facet().analyze();
+        let date = date_like_suffix.and_then(facet::parse_date_like_suffix);
+        (prefix, date)
+    }
 
-    /// <https://url.spec.whatwg.org/#query-percent-encode-set>
-    const QUERY: &AsciiSet = &CONTROLS_ESCAPE
-        .add(b' ')
-        .add(b'"')
-        .add(b'<')
-        .add(b'>')
-        .add(b'#');
+    /// Split the facet into hierarchy segments and the date-like suffix.
+    ///
+    /// The facet prefix without the date-like suffix is split into
+    /// segments at each `/` character. Empty segments, e.g. between
+    /// consecutive `/` characters or after a trailing `/`, are omitted.
+    /// The date-like suffix is returned verbatim including the leading
+    /// `@` character.
+    ///
+    /// Returns no segments for an empty facet or for a facet that
+    /// consists only of a date-like suffix.
+    ///
+    /// See also: [`facet::tokenize()`]
+    #[must_use]
+    pub fn facet_segments(&self) -> (Vec<&str>, Option<&str>) {
+        let (prefix, date_like_suffix) = self.facet().analyze();
+        let segments = prefix
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        (segments, date_like_suffix)
+    }
 
-    pub(super) const PROPS: &AsciiSet = &QUERY.add(b'&').add(b'=');
+    /// Return the identity of the tag as a borrowed key.
+    ///
+    /// The identity of a tag is determined by its facet and label,
+    /// properties are ignored. The key could be used for indexing
+    /// tags, e.g. in a `HashMap<(&str, &str), _>`.
+    #[must_use]
+    pub fn identity_key(&self) -> (&str, &str) {
+        (self.facet().as_ref(), self.label().as_ref())
+    }
 
-    /// <https://url.spec.whatwg.org/#path-percent-encode-set>
-    const PATH: &AsciiSet = &QUERY.add(b'`').add(b'?').add(b'{').add(b'}');
+    /// Compare tags chronologically, e.g. for timelines.
+    ///
+    /// Tags without a date-like facet are ordered before all tags with
+    /// a date-like facet. Tags with a date-like facet are ordered by their
+    /// date-like suffix in ascending order, i.e. older dates are ordered
+    /// before newer dates. The suffixes are compared textually and don't
+    /// need to encode a valid date.
+    ///
+    /// Ties are resolved by comparing the facets and then the labels.
+    /// Properties are not considered.
+    #[must_use]
+    pub fn cmp_chronological(&self, other: &Self) -> Ordering {
+        let (_, self_date_like_suffix) = self.facet().analyze();
+        let (_, other_date_like_suffix) = other.facet().analyze();
+        self_date_like_suffix
+            .cmp(&other_date_like_suffix)
+            .then_with(|| self.facet().cmp(other.facet()))
+            .then_with(|| self.label().cmp(other.label()))
+    }
 
-    pub(super) const FACET: &AsciiSet = PATH;
+    /// Assert that the tag is valid.
+    ///
+    /// Checks all components and the tag as a whole. In contrast to the
+    /// debug assertions in the accessors these checks are also performed
+    /// in release builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a descriptive message if the tag is invalid.
+    #[track_caller]
+    pub fn assert_valid(&self) -> &Self {
+        assert!(
+            self.label.is_valid(),
+            "invalid label {label:?}",
+            label = self.label
+        );
+        assert!(
+            self.facet.is_valid() && !facet::has_invalid_date_like_suffix(self.facet.as_ref()),
+            "invalid facet {facet:?}",
+            facet = self.facet
+        );
+        for prop in &self.props {
+            assert!(
+                prop.name.is_valid() && prop.is_valid(),
+                "invalid property name {name:?}",
+                name = prop.name
+            );
+        }
+        assert!(
+            self.is_valid(),
+            "invalid tag with label {label:?}, facet {facet:?}, and {props_len} properties",
+            label = self.label,
+            facet = self.facet,
+            props_len = self.props.len()
+        );
+        self
+    }
 }
 
 impl<F, L, N, V> Tag<F, L, N, V>
@@ -130,6 +568,230 @@ where
     N: Name,
     V: AsRef<str>,
 {
+    /// Validate the tag against application-specific constraints.
+    ///
+    /// The constraints of the `policy` are checked in addition to
+    /// the format-level rules of [`Self::validate()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`PolicyViolation`] if the tag is invalid or violates the `policy`.
+    pub fn validate_with(&self, policy: &TagPolicy) -> Result<(), PolicyViolation> {
+        let TagPolicy {
+            require_facet,
+            require_label,
+            forbid_empty_prop_values,
+            allow_facet_only_without_date,
+        } = policy;
+        match self.validate() {
+            Ok(()) => (),
+            Err(TagInvalidity::FacetOnlyWithoutDate) if *allow_facet_only_without_date => (),
+            Err(err) => return Err(PolicyViolation::Invalid(err)),
+        }
+        if *require_facet && !self.has_facet() {
+            return Err(PolicyViolation::MissingFacet);
+        }
+        if *require_label && !self.has_label() {
+            return Err(PolicyViolation::MissingLabel);
+        }
+        if *forbid_empty_prop_values && self.props().iter().any(Property::is_empty_value) {
+            return Err(PolicyViolation::EmptyPropValue);
+        }
+        Ok(())
+    }
+
+    /// Compare with a tag that might use different string types.
+    ///
+    /// All components are compared as string slices. Properties are
+    /// compared in order.
+    ///
+    /// See also: [`Self::str_eq_ignoring_prop_order()`]
+    #[must_use]
+    pub fn str_eq<F2, L2, N2, V2>(&self, other: &Tag<F2, L2, N2, V2>) -> bool
+    where
+        F2: Facet,
+        L2: Label,
+        N2: Name,
+        V2: AsRef<str>,
+    {
+        self.facet().as_ref() == other.facet().as_ref()
+            && self.label().as_ref() == other.label().as_ref()
+            && self.props().len() == other.props().len()
+            && self.props().iter().zip(other.props()).all(|(lhs, rhs)| {
+                lhs.name().as_ref() == rhs.name().as_ref()
+                    && lhs.value().as_ref() == rhs.value().as_ref()
+            })
+    }
+
+    /// Compare with a tag that might use different string types,
+    /// ignoring the order of properties.
+    ///
+    /// See also: [`Self::str_eq()`]
+    #[must_use]
+    pub fn str_eq_ignoring_prop_order<F2, L2, N2, V2>(&self, other: &Tag<F2, L2, N2, V2>) -> bool
+    where
+        F2: Facet,
+        L2: Label,
+        N2: Name,
+        V2: AsRef<str>,
+    {
+        if self.facet().as_ref() != other.facet().as_ref()
+            || self.label().as_ref() != other.label().as_ref()
+            || self.props().len() != other.props().len()
+        {
+            return false;
+        }
+        let mut lhs_props = self
+            .props()
+            .iter()
+            .map(|prop| (prop.name().as_ref(), prop.value().as_ref()))
+            .collect::<Vec<_>>();
+        let mut rhs_props = other
+            .props()
+            .iter()
+            .map(|prop| (prop.name().as_ref(), prop.value().as_ref()))
+            .collect::<Vec<_>>();
+        lhs_props.sort_unstable();
+        rhs_props.sort_unstable();
+        lhs_props == rhs_props
+    }
+
+    /// Check if all information of this tag is also contained in another tag.
+    ///
+    /// This tag is subsumed by `other` if all of the following conditions hold:
+    ///
+    /// - Both tags have the same label, which might be empty.
+    /// - This tag has no facet, or the same facet as `other`, or a facet
+    ///   without a date-like suffix that equals the facet of `other` after
+    ///   stripping its date-like suffix.
+    /// - Each property of this tag is also a property of `other`.
+    ///
+    /// Every tag is subsumed by itself.
+    #[must_use]
+    pub fn is_subsumed_by(&self, other: &Self) -> bool {
+        if self.label() != other.label() {
+            return false;
+        }
+        let facet_subsumed = !self.has_facet()
+            || self.facet() == other.facet()
+            || (!self.facet().has_date_like_suffix()
+                && self.facet().as_ref() == other.facet().prefix_without_date_suffix());
+        facet_subsumed
+            && self.props().iter().all(|prop| {
+                other.props().iter().any(|other_prop| {
+                    prop.name() == other_prop.name()
+                        && prop.value().as_ref() == other_prop.value().as_ref()
+                })
+            })
+    }
+
+    /// Sort the properties into canonical order.
+    ///
+    /// Properties are sorted by name and then by value. The relative
+    /// order of properties with equal names is not preserved.
+    pub fn canonicalize(&mut self) {
+        self.props.sort_by(Property::cmp_canonical);
+    }
+
+    fn cmp_props(&self, other: &Self) -> Ordering {
+        self.props
+            .iter()
+            .map(|Property { name, value }| (name.as_ref(), value.as_ref()))
+            .cmp(
+                other
+                    .props
+                    .iter()
+                    .map(|Property { name, value }| (name.as_ref(), value.as_ref())),
+            )
+    }
+
+    /// Compare tags in canonical order.
+    ///
+    /// Canonical ordering:
+    ///   1. Tags without a facet
+    ///   2. Tags with a non-date-like facet
+    ///   3. Tags with a date-like facet (by descending suffix)
+    ///
+    /// Within each group tags are sorted by facet, then by label. For tags with
+    /// equal facets those with a label are sorted before those without a label.
+    ///
+    /// Tags with a date-like facet are sorted in descending order by their
+    /// date-like suffix, i.e. newer dates are sorted before older dates.
+    ///
+    /// Tags with equal facets and labels are sorted by their properties.
+    #[must_use]
+    pub fn cmp_canonical(&self, other: &Self) -> Ordering {
+        self.cmp_canonical_with_options(other, &DedupOptions::default())
+    }
+
+    /// Compare tags in canonical order with custom options.
+    ///
+    /// See also: [`Self::cmp_canonical()`]
+    #[must_use]
+    pub fn cmp_canonical_with_options(&self, other: &Self, options: &DedupOptions) -> Ordering {
+        let DedupOptions { labels_first } = options;
+        let (lhs, rhs) = (self, other);
+        match (lhs.facet().analyze(), rhs.facet().analyze()) {
+            ((_, Some(lhs_suffix)), (_, Some(rhs_suffix))) => {
+                // Descending order by decimal digits encoded as ASCII chars
+                let ordering = rhs_suffix.cmp(lhs_suffix);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            ((_, None), (_, Some(_))) => {
+                return Ordering::Less;
+            }
+            ((_, Some(_)), (_, None)) => {
+                return Ordering::Greater;
+            }
+            ((_, None), (_, None)) => (),
+        }
+        if rhs.has_facet() {
+            if lhs.has_facet() {
+                let ordering = lhs.facet().cmp(rhs.facet());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            } else {
+                return Ordering::Less;
+            }
+        } else if lhs.has_facet() {
+            return Ordering::Greater;
+        }
+        // Tags with labels before tags without labels, unless reversed
+        debug_assert_eq!(lhs.facet(), rhs.facet());
+        let ordering = match (lhs.has_label(), rhs.has_label()) {
+            (true, true) => lhs.label().cmp(rhs.label()),
+            (true, false) if *labels_first => Ordering::Less,
+            (false, true) if *labels_first => Ordering::Greater,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => Ordering::Equal,
+        };
+        // Tags with equal facets and labels by their properties
+        ordering.then_with(|| lhs.cmp_props(rhs))
+    }
+
+    /// Compare tags according to the given policy.
+    #[must_use]
+    pub fn cmp_by(&self, other: &Self, policy: SortPolicy) -> Ordering {
+        match policy {
+            SortPolicy::Canonical => self.cmp_canonical(other),
+            SortPolicy::Chronological => self.cmp_chronological(other),
+            SortPolicy::Label => self
+                .label()
+                .cmp(other.label())
+                .then_with(|| self.facet().cmp(other.facet()))
+                .then_with(|| self.cmp_props(other)),
+            SortPolicy::Facet => self
+                .facet()
+                .cmp(other.facet())
+                .then_with(|| self.label().cmp(other.label()))
+                .then_with(|| self.cmp_props(other)),
+        }
+    }
+
     /// Encode a tag as a string.
     ///
     /// The tag must be valid.
@@ -138,7 +800,114 @@ where
     ///
     /// Returns an [`fmt::Error`] if writing into the buffer fails.
     pub fn encode_into<W: fmt::Write>(&self, write: &mut W) -> fmt::Result {
-        debug_assert!(self.is_valid());
+        self.encode_props_into(write, self.props(), PropSeparator::default())
+    }
+
+    /// Encode a tag into a `String` buffer with custom options.
+    ///
+    /// See also: [`Self::encode_into()`]
+    ///
+    /// The tag must be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`fmt::Error`] if writing into the buffer fails.
+    pub fn encode_into_with_options<W: fmt::Write>(
+        &self,
+        write: &mut W,
+        options: &EncodeOptions,
+    ) -> fmt::Result {
+        let EncodeOptions {
+            prop_separator,
+            canonical_prop_order,
+        } = options;
+        if *canonical_prop_order {
+            let mut props = self.props().iter().collect::<Vec<_>>();
+            props.sort_by(|lhs, rhs| lhs.cmp_canonical(rhs));
+            return self.encode_props_into(write, props, *prop_separator);
+        }
+        self.encode_props_into(write, self.props(), *prop_separator)
+    }
+
+    /// Report all characters that are percent-encoded when encoding the tag.
+    ///
+    /// The characters are reported in the order of the components
+    /// facet, properties, and label, i.e. in the order in which they
+    /// appear in the encoded tag. Only the default property separator
+    /// `&` is considered.
+    ///
+    /// This is a diagnostic for explaining why the encoded tag differs
+    /// from its components.
+    #[must_use]
+    pub fn encoding_report(&self) -> Vec<encoding::EscapedChar> {
+        use encoding::{escaped_chars, Component};
+        let mut report =
+            escaped_chars(Component::Facet, None, self.facet().as_ref()).collect::<Vec<_>>();
+        for (prop_index, prop) in self.props().iter().enumerate() {
+            report.extend(escaped_chars(
+                Component::PropName,
+                Some(prop_index),
+                prop.name().as_ref(),
+            ));
+            report.extend(escaped_chars(
+                Component::PropValue,
+                Some(prop_index),
+                prop.value().as_ref(),
+            ));
+        }
+        report.extend(escaped_chars(Component::Label, None, self.label().as_ref()));
+        report
+    }
+
+    /// Encode a tag as a string with custom options.
+    ///
+    /// The tag must be valid.
+    #[must_use]
+    pub fn encode_with_options(&self, options: &EncodeOptions) -> String {
+        let mut encoded = String::new();
+        let result = self.encode_into_with_options(&mut encoded, options);
+        // Writing into a `String` never fails
+        debug_assert!(result.is_ok());
+        encoded
+    }
+
+    /// Encode a tag into a `String` buffer with a custom order of properties.
+    ///
+    /// The properties are encoded in the order defined by `compare`
+    /// without modifying the tag. Properties that compare equal keep
+    /// their relative order.
+    ///
+    /// The tag must be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`fmt::Error`] if writing into the buffer fails.
+    pub fn encode_into_ordered<W: fmt::Write>(
+        &self,
+        write: &mut W,
+        mut compare: impl FnMut(&Property<N, V>, &Property<N, V>) -> Ordering,
+    ) -> fmt::Result {
+        let mut props = self.props().iter().collect::<Vec<_>>();
+        props.sort_by(|lhs, rhs| compare(lhs, rhs));
+        self.encode_props_into(write, props, PropSeparator::default())
+    }
+
+    /// Check if the tag is either valid or has at least a facet.
+    ///
+    /// Tags with only a facet without a date-like suffix are invalid, but
+    /// they could still be encoded if permitted by a [`TagPolicy`], see
+    /// [`TagPolicy::allow_facet_only_without_date`].
+    fn is_encodable(&self) -> bool {
+        self.is_valid() || self.has_facet()
+    }
+
+    fn encode_props_into<'a, W: fmt::Write>(
+        &'a self,
+        write: &mut W,
+        props: impl IntoIterator<Item = &'a Property<N, V>>,
+        prop_separator: PropSeparator,
+    ) -> fmt::Result {
+        debug_assert!(self.is_encodable());
         let encoded_label = percent_encode(self.label().as_ref().as_bytes(), encoding::LABEL);
         let encoded_facet = percent_encode(self.facet().as_ref().as_bytes(), encoding::FACET);
         if !self.has_props() {
@@ -149,12 +918,13 @@ where
                 return write.write_fmt(format_args!("{encoded_facet}"));
             }
         }
-        let encoded_props_iter = self.props().iter().map(|Property { name, value }| {
-            let encoded_name = percent_encode(name.as_ref().as_bytes(), encoding::PROPS);
-            let encoded_value = percent_encode(value.as_ref().as_bytes(), encoding::PROPS);
-            format_compact!("{encoded_name}={encoded_value}")
-        });
-        let encoded_props = itertools::join(encoded_props_iter, "&");
+        let mut encoded_props = String::new();
+        for (i, prop) in props.into_iter().enumerate() {
+            if i > 0 {
+                encoded_props.push(prop_separator.as_char());
+            }
+            prop.encode_into_separated(&mut encoded_props, prop_separator)?;
+        }
         if self.has_label() {
             write.write_fmt(format_args!(
                 "{encoded_facet}?{encoded_props}#{encoded_label}"
@@ -171,20 +941,305 @@ where
     pub fn encode(&self) -> String {
         self.to_string()
     }
-}
 
-impl<F, L, N, V> fmt::Display for Tag<F, L, N, V>
-where
-    F: Facet,
-    L: Label,
-    N: Name,
+    /// Encode a tag as a string, borrowing it if possible.
+    ///
+    /// Returns the facet verbatim without allocating a new string for
+    /// tags with only a facet that does not need to be escaped, e.g.
+    /// `@20220625`. Otherwise the tag is encoded like [`Self::encode()`].
+    ///
+    /// The tag must be valid.
+    #[must_use]
+    pub fn encode_cow(&self) -> Cow<'_, str> {
+        if self.has_label() || self.has_props() {
+            return self.encode().into();
+        }
+        debug_assert!(self.is_encodable());
+        percent_encode(self.facet().as_ref().as_bytes(), encoding::FACET).into()
+    }
+
+    /// Encode a tag as a string after checking that it is valid.
+    ///
+    /// Use this function instead of [`Self::encode()`] if the
+    /// tag might be invalid and the validity should be checked
+    /// at runtime, also in release builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is invalid, see [`Self::assert_valid()`].
+    #[must_use]
+    #[track_caller]
+    pub fn encode_checked(&self) -> String {
+        self.assert_valid().encode()
+    }
+
+    /// Convert the tag into an URL.
+    ///
+    /// The URL has a dummy scheme and contains the encoded components
+    /// of the tag as its path, query, and fragment.
+    ///
+    /// The tag must be valid.
+    #[must_use]
+    pub fn to_url(&self) -> Url {
+        debug_assert!(self.is_encodable());
+        let mut url = dummy_base_url().clone();
+        let encoded_facet = percent_encode(self.facet().as_ref().as_bytes(), encoding::FACET);
+        url.set_path(&format!("/{encoded_facet}"));
+        if self.has_props() {
+            url.set_query(Some(&itertools::join(self.props(), "&")));
+        }
+        if self.has_label() {
+            let encoded_label = percent_encode(self.label().as_ref().as_bytes(), encoding::LABEL);
+            url.set_fragment(Some(&encoded_label.to_string()));
+        }
+        url
+    }
+
+    /// Encode a tag into an I/O sink.
+    ///
+    /// Writes the UTF-8 encoded string directly into the sink
+    /// without allocating an intermediate buffer for the whole
+    /// tag. Use [`Self::encode_into()`] for building strings.
+    ///
+    /// The tag must be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] if writing into the sink fails.
+    pub fn write_to<W: io::Write>(&self, write: &mut W) -> io::Result<()> {
+        write_fmt_to_io(write, |adapter| self.encode_into(adapter))
+    }
+}
+
+impl<F, L, N, V> From<&Tag<F, L, N, V>> for Url
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+    V: AsRef<str>,
+{
+    fn from(from: &Tag<F, L, N, V>) -> Self {
+        from.to_url()
+    }
+}
+
+/// Forwards [`fmt::Write`] output into an [`io::Write`] sink.
+///
+/// Captures the first I/O error, which would otherwise get lost
+/// when being converted into an opaque [`fmt::Error`].
+struct IoWriteAdapter<'w, W> {
+    write: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoWriteAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+fn write_fmt_to_io<W: io::Write>(
+    write: &mut W,
+    encode_into: impl FnOnce(&mut IoWriteAdapter<'_, W>) -> fmt::Result,
+) -> io::Result<()> {
+    let mut adapter = IoWriteAdapter { write, error: None };
+    encode_into(&mut adapter).map_err(|fmt::Error| {
+        adapter
+            .error
+            .take()
+            .unwrap_or_else(|| io::Error::other("formatting failed"))
+    })
+}
+
+impl<F, L, N, V> fmt::Display for Tag<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
     V: AsRef<str>,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.encode_into(f)
+        let Some(width) = f.width() else {
+            return self.encode_into(f);
+        };
+        // Padding requires to know the length of the encoded string in advance.
+        // The precision is ignored, i.e. the encoded tag is never truncated.
+        let mut encoded = String::new();
+        self.encode_into(&mut encoded)?;
+        let padding = width.saturating_sub(encoded.chars().count());
+        let (pre_padding, post_padding) = match f.align() {
+            None | Some(fmt::Alignment::Left) => (0, padding),
+            Some(fmt::Alignment::Right) => (padding, 0),
+            Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        };
+        let fill = f.fill();
+        for _ in 0..pre_padding {
+            fmt::Write::write_char(f, fill)?;
+        }
+        f.write_str(&encoded)?;
+        for _ in 0..post_padding {
+            fmt::Write::write_char(f, fill)?;
+        }
+        Ok(())
+    }
+}
+
+/// Policies for sorting tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortPolicy {
+    /// Canonical order, see [`Tag::cmp_canonical()`].
+    #[default]
+    Canonical,
+
+    /// Chronological order, see [`Tag::cmp_chronological()`].
+    Chronological,
+
+    /// Alphabetical order by label, then by facet, then by properties.
+    ///
+    /// Tags without a label are sorted first.
+    Label,
+
+    /// Alphabetical order by facet, then by label, then by properties.
+    ///
+    /// Tags without a facet are sorted first.
+    Facet,
+}
+
+/// Options for reordering and deduplicating tags
+///
+/// See also: [`DecodedTags::reorder_and_dedup_with_options()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupOptions {
+    /// Sort tags with a label before tags without a label if their
+    /// facets are equal.
+    ///
+    /// Enabled by default.
+    pub labels_first: bool,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        Self { labels_first: true }
     }
 }
 
+/// Policies for dropping tags when re-encoding with limited space
+///
+/// See also: [`DecodedTags::reencode_within()`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TruncatePolicy {
+    /// Keep the current order and drop tags from the end.
+    #[default]
+    DropTail,
+
+    /// Drop tags with the oldest date-like facet first.
+    ///
+    /// Tags without a date-like facet are dropped last, starting from
+    /// the end.
+    DropOldestFirst,
+}
+
+/// Policies for resolving properties with duplicate names
+///
+/// See also: [`Tag::enforce_unique_prop_names()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Keep the first property and drop all subsequent properties
+    /// with the same name.
+    KeepFirst,
+
+    /// Keep the last property and drop all preceding properties
+    /// with the same name.
+    KeepLast,
+
+    /// Reject properties with duplicate names.
+    Reject,
+}
+
+/// A property name that occurs more than once
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+#[display("duplicate property name: {name}")]
+pub struct DuplicatePropName {
+    /// The duplicate name.
+    #[error(not(source))]
+    pub name: String,
+}
+
+/// Reasons for an invalid tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+pub enum TagInvalidity {
+    /// Invalid facet.
+    #[display("invalid facet: {_0}")]
+    Facet(FacetInvalidity),
+
+    /// Invalid label.
+    #[display("invalid label: {_0}")]
+    Label(LabelInvalidity),
+
+    /// Empty property name or with leading/trailing whitespace.
+    #[display("invalid property name")]
+    PropName,
+
+    /// Neither a label nor a facet.
+    #[display("missing label and facet")]
+    MissingLabelAndFacet,
+
+    /// Only a facet without a date-like suffix, neither a label nor properties.
+    #[display("facet without date-like suffix and neither label nor properties")]
+    FacetOnlyWithoutDate,
+}
+
+/// Application-specific constraints for tags
+///
+/// All constraints and relaxations are disabled by default, i.e.
+/// only the validity rules of the format apply.
+///
+/// See also: [`Tag::validate_with()`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TagPolicy {
+    /// Require a non-empty facet.
+    pub require_facet: bool,
+
+    /// Require a non-empty label.
+    pub require_label: bool,
+
+    /// Reject properties with an empty value.
+    pub forbid_empty_prop_values: bool,
+
+    /// Accept tags with only a facet without a date-like suffix.
+    ///
+    /// Relaxes the validity rule [`TagInvalidity::FacetOnlyWithoutDate`]
+    /// for structured facets like `genre/techno` that are meaningful
+    /// on their own. Such tags can be encoded, but are only decoded by
+    /// [`Tag::decode_str_with_policy()`] and [`DecodedTags::decode_str_with_policy()`].
+    /// They are still rejected by [`Tag::decode_str()`] and [`Tag::validate()`].
+    pub allow_facet_only_without_date: bool,
+}
+
+/// Violations of a [`TagPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Error)]
+pub enum PolicyViolation {
+    /// Invalid tag.
+    #[display("{_0}")]
+    Invalid(TagInvalidity),
+
+    /// Missing facet.
+    #[display("missing facet")]
+    MissingFacet,
+
+    /// Missing label.
+    #[display("missing label")]
+    MissingLabel,
+
+    /// Property with an empty value.
+    #[display("empty property value")]
+    EmptyPropValue,
+}
+
 /// A decoding error
 #[derive(Debug, Display, Error)]
 pub enum DecodeError {
@@ -194,6 +1249,157 @@ pub enum DecodeError {
 
     /// Parse error.
     Parse(anyhow::Error),
+
+    /// Rejected by a custom validator.
+    #[display("rejected: {_0}")]
+    Rejected(#[error(not(source))] String),
+
+    /// Valid, but not in canonical form.
+    ///
+    /// See also: [`Tag::decode_str_canonical()`]
+    #[display("non-canonical, expected '{canonical}'")]
+    NonCanonical {
+        /// The canonical form.
+        #[error(not(source))]
+        canonical: String,
+    },
+}
+
+/// A decoded component of a tag
+///
+/// See also: [`Tag::decode_str_with_validator()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedComponent<'a> {
+    /// The facet.
+    Facet(&'a str),
+
+    /// The label.
+    Label(&'a str),
+
+    /// A property.
+    Prop {
+        /// The name.
+        name: &'a str,
+
+        /// The value.
+        value: &'a str,
+    },
+}
+
+/// Options for decoding tags
+///
+/// The default options are permissive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct DecodeOptions {
+    /// Reject over-encoded input.
+    ///
+    /// Input is considered as over-encoded if a component contains
+    /// percent-encoded characters that would not be escaped when
+    /// encoding the component, e.g. `%41` instead of `A`, or escapes
+    /// with lowercase hex digits, e.g. `%c3%a4` instead of `%C3%A4`.
+    /// Re-encoding of such input is not idempotent.
+    pub reject_overencoding: bool,
+
+    /// Reject facets with multiple date-like suffixes.
+    ///
+    /// See also: [`facet::count_date_like_suffixes()`]
+    pub reject_multiple_date_like_suffixes: bool,
+
+    /// The separator between properties.
+    pub prop_separator: PropSeparator,
+
+    /// Replace invalid UTF-8 sequences instead of rejecting them.
+    ///
+    /// Percent-encoded bytes that do not form valid UTF-8 sequences,
+    /// e.g. Latin-1 characters like `%E9`, are replaced with U+FFFD.
+    /// The original bytes are lost and re-encoding the decoded tag
+    /// will not restore them. Only use this option for salvaging tags
+    /// from legacy data.
+    pub lossy_utf8: bool,
+
+    /// Stop decoding tags at the last newline character.
+    ///
+    /// Only affects [`DecodedTags::decode_str_with_options()`]. When enabled
+    /// (default) only the last line of a text field may contain tags and
+    /// everything up to and including the last newline character is
+    /// preserved as the undecoded prefix. When disabled newline characters
+    /// are treated like any other whitespace and tags may span multiple
+    /// lines.
+    pub stop_at_newline: bool,
+
+    /// Trim leading/trailing whitespace from property values.
+    ///
+    /// Property names must not contain leading/trailing whitespace,
+    /// but values may. By default values are decoded exactly, because
+    /// whitespace might be meaningful.
+    pub trim_values: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            reject_overencoding: false,
+            reject_multiple_date_like_suffixes: false,
+            prop_separator: PropSeparator::Ampersand,
+            lossy_utf8: false,
+            stop_at_newline: true,
+            trim_values: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Strict options for decoding only the canonical form.
+    ///
+    /// Rejects over-encoded input and facets with multiple date-like
+    /// suffixes. Properties are separated by `&`.
+    ///
+    /// See also: [`EncodeOptions::canonical()`]
+    #[must_use]
+    pub const fn strict() -> Self {
+        Self {
+            reject_overencoding: true,
+            reject_multiple_date_like_suffixes: true,
+            prop_separator: PropSeparator::Ampersand,
+            lossy_utf8: false,
+            stop_at_newline: true,
+            trim_values: false,
+        }
+    }
+}
+
+/// Options for encoding tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// The separator between properties.
+    pub prop_separator: PropSeparator,
+
+    /// Encode properties in canonical order without modifying the tag.
+    ///
+    /// See also: [`Tag::canonicalize()`]
+    pub canonical_prop_order: bool,
+}
+
+impl EncodeOptions {
+    /// Options for encoding tags in canonical form.
+    ///
+    /// The canonical form is defined as follows:
+    ///
+    /// - Properties are sorted by name and then by value.
+    /// - Properties are separated by `&`.
+    /// - Properties with an empty value are encoded as `name=`.
+    /// - Only characters that need to be escaped are percent-encoded.
+    ///
+    /// Decoding the canonical form with [`DecodeOptions::strict()`]
+    /// and encoding it again results in the same string.
+    #[must_use]
+    pub const fn canonical() -> Self {
+        Self {
+            prop_separator: PropSeparator::Ampersand,
+            canonical_prop_order: true,
+        }
+    }
 }
 
 static DUMMY_BASE_URL_WITH_ABSOLUTE_PATH: OnceLock<Url> = OnceLock::new();
@@ -226,178 +1432,768 @@ where
     ///
     /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag.
     pub fn decode_str(encoded: &str) -> Result<Self, DecodeError> {
-        let encoded_trimmed = encoded.trim();
-        if encoded_trimmed != encoded {
-            return Err(DecodeError::Parse(anyhow!(
-                "leading/trailing whitespace in encoded input"
-            )));
-        }
-        if encoded_trimmed.is_empty() {
-            return Err(DecodeError::Parse(anyhow!("empty encoded input")));
-        }
-        if encoded_trimmed.as_bytes().first() == Some(&b'/') {
-            return Err(DecodeError::Parse(anyhow!(
-                "encoded input starts with leading slash `/`"
-            )));
-        }
-        let parse_options = Url::options().base_url(Some(dummy_base_url()));
-        let url: Url = parse_options
-            .parse(encoded)
-            .map_err(Into::into)
-            .map_err(DecodeError::Parse)?;
-        if url.scheme() != dummy_base_url().scheme() || url.has_host() || !url.username().is_empty()
+        Self::decode_str_with_options(encoded, &DecodeOptions::default())
+    }
+
+    /// Decode tags lazily from pre-split tokens.
+    ///
+    /// Applies [`Self::decode_str()`] to each token. The tokens are
+    /// decoded strictly, i.e. they are not trimmed. This allows callers
+    /// to use their own tokenizer, e.g. for custom separators.
+    pub fn decode_tokens<'a, I>(tokens: I) -> impl Iterator<Item = Result<Self, DecodeError>>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        tokens.into_iter().map(Self::decode_str)
+    }
+
+    /// Decode a tag from an encoded token, replacing invalid UTF-8 sequences.
+    ///
+    /// The decoding is lossy, see [`DecodeOptions::lossy_utf8`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag.
+    pub fn decode_str_lossy(encoded: &str) -> Result<Self, DecodeError> {
+        Self::decode_str_with_options(
+            encoded,
+            &DecodeOptions {
+                lossy_utf8: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Decode a tag from an encoded token in canonical form.
+    ///
+    /// Only accepts input that re-encodes to exactly the same string
+    /// with [`EncodeOptions::canonical()`], i.e. decoding and encoding
+    /// is idempotent. This also rejects properties that are not in
+    /// canonical order or without a `=` separator.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag.
+    /// Returns [`DecodeError::NonCanonical`] with the canonical form if the
+    /// input is valid but not canonical.
+    pub fn decode_str_canonical(encoded: &str) -> Result<Self, DecodeError> {
+        let tag = Self::decode_str(encoded)?;
+        let canonical = tag.encode_with_options(&EncodeOptions::canonical());
+        if canonical != encoded {
+            return Err(DecodeError::NonCanonical { canonical });
+        }
+        Ok(tag)
+    }
+
+    /// Decode a tag from an encoded token after repairing common mistakes.
+    ///
+    /// In contrast to [`Self::decode_str()`] leading/trailing whitespace
+    /// and leading slashes `/` are stripped from the input before decoding
+    /// it. This is useful for importing tags that have been typed by users.
+    ///
+    /// Returns the decoded tag and whether the input needed to be repaired.
+    /// Repaired tags should be re-encoded to store their cleaned form.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the repaired input cannot be decoded as a valid tag.
+    pub fn decode_str_repaired(encoded: &str) -> Result<(Self, bool), DecodeError> {
+        let repaired = encoded.trim().trim_start_matches('/').trim_start();
+        let tag = Self::decode_str(repaired)?;
+        Ok((tag, repaired != encoded))
+    }
+
+    /// Decode a tag from an encoded token and validate its components.
+    ///
+    /// The `validator` is invoked for the non-empty facet, the non-empty
+    /// label, and each property of the decoded tag. The tag is rejected
+    /// if the `validator` returns an error message for any component.
+    ///
+    /// See also: [`Self::decode_str()`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag
+    /// or [`DecodeError::Rejected`] if it has been rejected by the `validator`.
+    pub fn decode_str_with_validator(
+        encoded: &str,
+        validator: &dyn Fn(&DecodedComponent<'_>) -> Result<(), String>,
+    ) -> Result<Self, DecodeError> {
+        let tag = Self::decode_str(encoded)?;
+        if tag.has_facet() {
+            validator(&DecodedComponent::Facet(tag.facet().as_ref()))
+                .map_err(DecodeError::Rejected)?;
+        }
+        if tag.has_label() {
+            validator(&DecodedComponent::Label(tag.label().as_ref()))
+                .map_err(DecodeError::Rejected)?;
+        }
+        for prop in tag.props() {
+            validator(&DecodedComponent::Prop {
+                name: prop.name().as_ref(),
+                value: prop.value().as_ref(),
+            })
+            .map_err(DecodeError::Rejected)?;
+        }
+        Ok(tag)
+    }
+
+    /// Decode a tag from an encoded token with custom options.
+    ///
+    /// See also: [`Self::decode_str()`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded as a valid tag.
+    pub fn decode_str_with_options(
+        encoded: &str,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let url = parse_encoded_url(encoded)?;
+        Self::decode_url_with_options(&url, options)
+    }
+
+    /// Decode a tag from an encoded token, validating it with a policy.
+    ///
+    /// In contrast to [`Self::decode_str()`] the validity of the decoded
+    /// tag is checked by [`Self::validate_with()`]. This allows to decode
+    /// tags that are only valid according to the `policy`, e.g. tags with
+    /// only a facet if [`TagPolicy::allow_facet_only_without_date`] is
+    /// enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the encoded input cannot be decoded
+    /// as a tag that is valid according to the `policy`.
+    pub fn decode_str_with_policy(encoded: &str, policy: &TagPolicy) -> Result<Self, DecodeError> {
+        let url = parse_encoded_url(encoded)?;
+        let tag = Self::decode_url_components(&url, &DecodeOptions::default())?;
+        tag.validate_with(policy)
+            .map_err(|violation| match violation {
+                PolicyViolation::Invalid(_) => DecodeError::InvalidTag,
+                violation => DecodeError::Rejected(violation.to_string()),
+            })?;
+        Ok(tag)
+    }
+
+    fn decode_url_with_options(url: &Url, options: &DecodeOptions) -> Result<Self, DecodeError> {
+        let tag = Self::decode_url_components(url, options)?;
+        if !tag.is_valid() {
+            return Err(DecodeError::InvalidTag);
+        }
+        Ok(tag)
+    }
+
+    /// Decode and validate the components, but not the tag as a whole.
+    fn decode_url_components(url: &Url, options: &DecodeOptions) -> Result<Self, DecodeError> {
+        if url.scheme() != dummy_base_url().scheme()
+            || url.has_host()
+            || !url.username().is_empty()
+            || !url.path().starts_with('/')
         {
             return Err(DecodeError::Parse(anyhow!("invalid encoded input")));
         }
         let fragment = url.fragment().unwrap_or_default();
-        debug_assert_eq!(fragment.trim(), fragment);
-        let label_encoded = fragment.as_bytes();
-        let label = percent_decode(label_encoded)
-            .decode_utf8()
-            .map_err(Into::into)
-            .map_err(DecodeError::Parse)?;
-        if !label::is_valid(&label) {
-            return Err(DecodeError::Parse(anyhow!("invalid label '{label}'")));
-        }
         // The leading slash in the path from the dummy base URL needs to be skipped.
         let path = url.path();
         debug_assert!(!path.is_empty());
-        debug_assert_eq!(path.trim(), path);
         debug_assert_eq!(path.as_bytes()[0], b'/');
-        let facet_encoded = &url.path().as_bytes()[1..];
-        let facet = percent_decode(facet_encoded)
-            .decode_utf8()
-            .map_err(Into::into)
-            .map_err(DecodeError::Parse)?;
-        if !facet::is_valid(&facet) {
-            return Err(DecodeError::Parse(anyhow!("invalid facet '{facet}'")));
-        }
-        if facet::has_invalid_date_like_suffix(&facet) {
-            return Err(DecodeError::Parse(anyhow!(
-                "facet with invalid date-like suffix '{facet}'"
-            )));
-        }
-        let mut props = vec![];
         let query = url.query().unwrap_or_default();
+        // Whitespace is always percent-encoded in URLs
+        debug_assert_eq!(fragment.trim(), fragment);
+        debug_assert_eq!(path.trim(), path);
         debug_assert_eq!(query.trim(), query);
-        if !query.is_empty() {
-            let query_encoded = query.as_bytes();
-            for name_value_encoded in query_encoded.split(|b| *b == b'&') {
-                let mut name_value_encoded_split = name_value_encoded.split(|b| *b == b'=');
-                let Some(name_encoded) = name_value_encoded_split.next() else {
-                    return Err(DecodeError::Parse(anyhow!("missing property name")));
-                };
-                let value_encoded = name_value_encoded_split.next().unwrap_or_default();
-                if name_value_encoded_split.next().is_some() {
-                    return Err(DecodeError::Parse(anyhow!(
-                        "malformed name=value property '{name_value}'",
-                        name_value = percent_decode(name_value_encoded)
-                            .decode_utf8()
-                            .unwrap_or_default()
-                    )));
-                }
-                let name = percent_decode(name_encoded)
-                    .decode_utf8()
-                    .map_err(Into::into)
-                    .map_err(DecodeError::Parse)?;
-                if !props::is_name_valid(&name) {
-                    return Err(DecodeError::Parse(anyhow!(
-                        "invalid property name '{name}'"
-                    )));
-                }
-                let value = percent_decode(value_encoded)
-                    .decode_utf8()
-                    .map_err(Into::into)
-                    .map_err(DecodeError::Parse)?;
-                let prop = Property {
-                    name: Name::from_cow_str(name),
-                    value: Value::from_cow_str(value),
-                };
-                props.push(prop);
-            }
-        }
-        let tag = Self {
-            label: <L as Label>::from_cow_str(label),
-            facet: <F as Facet>::from_cow_str(facet),
-            props,
-        };
-        if !tag.is_valid() {
-            return Err(DecodeError::InvalidTag);
-        }
-        Ok(tag)
+        decode_components(&path[1..], query, fragment, options).map(TagRef::into_owned)
     }
 }
 
-impl<F, L, N, V> FromStr for Tag<F, L, N, V>
+impl<F, L, N, V> TryFrom<&Url> for Tag<F, L, N, V>
 where
     F: Facet,
     L: Label,
     N: Name,
     V: Value,
 {
-    type Err = DecodeError;
+    type Error = DecodeError;
 
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        // This implementation permits leading/trailing whitespace,
-        // other than `Tag::decode_str()` which is more strict.
-        Tag::decode_str(input.trim())
+    /// Decode a tag from an URL.
+    ///
+    /// Only URLs in the form produced by [`Tag::to_url()`] are accepted.
+    /// The components are validated in the same way as for
+    /// [`Tag::decode_str()`].
+    fn try_from(url: &Url) -> Result<Self, Self::Error> {
+        Self::decode_url_with_options(url, &DecodeOptions::default())
     }
 }
 
-/// Tags decoded from a text field
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct DecodedTags<F, L, N, V> {
-    /// Valid, decoded tags
-    pub tags: Vec<Tag<F, L, N, V>>,
+/// Check if a token looks like an encoded tag.
+///
+/// A cheap heuristic that could be used for filtering tokens before
+/// decoding them. The token must be non-empty, must neither start with
+/// a slash `/` nor have leading/trailing whitespace, and must contain
+/// a `#` or `?` character or end with a date-like suffix.
+///
+/// This check is necessary but not sufficient, i.e. all valid encoded
+/// tags pass it, but [`Tag::decode_str()`] may still reject tokens that
+/// look like a tag.
+#[must_use]
+pub fn looks_like_tag(token: &str) -> bool {
+    !token.is_empty()
+        && !token.starts_with('/')
+        && token.trim() == token
+        && (token.contains(['#', '?']) || facet::has_date_like_suffix(token))
+}
 
-    /// The remaining, undecoded prefix.
-    pub undecoded_prefix: String,
+/// Check if two encoded tags are semantically equivalent.
+///
+/// Both tags are decoded and compared after canonicalizing their
+/// properties, see [`Tag::canonicalize()`]. Differences in the
+/// encoding of the components or in the order of the properties
+/// are ignored.
+///
+/// Returns `false` if either of the tags could not be decoded.
+#[must_use]
+pub fn encoded_tags_equivalent(lhs: &str, rhs: &str) -> bool {
+    type StdTag = Tag<StdFacet, StdLabel, StdName, String>;
+    let (Ok(mut lhs), Ok(mut rhs)) = (StdTag::decode_str(lhs), StdTag::decode_str(rhs)) else {
+        return false;
+    };
+    lhs.canonicalize();
+    rhs.canonicalize();
+    lhs == rhs
 }
 
-const JOIN_ENCODED_TOKENS_CHAR: char = ' ';
+/// Group tags by their label.
+///
+/// Tags without a label are skipped. The tags of each group appear
+/// in the same order as in `tags`.
+///
+/// See also: [`index_by_facet_prefix()`]
+#[must_use]
+#[allow(clippy::type_complexity)]
+pub fn index_by_label<'a, F, L, N, V>(
+    tags: impl IntoIterator<Item = &'a Tag<F, L, N, V>>,
+) -> HashMap<&'a str, Vec<&'a Tag<F, L, N, V>>>
+where
+    F: Facet + 'a,
+    L: Label + 'a,
+    N: Name + 'a,
+    V: 'a,
+{
+    let mut index = HashMap::<_, Vec<_>>::new();
+    for tag in tags.into_iter().filter(|tag| tag.has_label()) {
+        index.entry(tag.label().as_ref()).or_default().push(tag);
+    }
+    index
+}
 
-impl<F, L, N, V> DecodedTags<F, L, N, V>
+/// Group tags by their facet prefix.
+///
+/// The prefix of a facet is the facet without its date-like suffix,
+/// see [`facet::prefix_without_date_suffix()`]. Tags with an empty
+/// prefix are skipped. The tags of each group appear in the same
+/// order as in `tags`.
+///
+/// See also: [`index_by_label()`]
+#[must_use]
+#[allow(clippy::type_complexity)]
+pub fn index_by_facet_prefix<'a, F, L, N, V>(
+    tags: impl IntoIterator<Item = &'a Tag<F, L, N, V>>,
+) -> HashMap<&'a str, Vec<&'a Tag<F, L, N, V>>>
 where
-    F: Facet,
-    L: Label,
-    N: Name,
-    V: Value,
+    F: Facet + 'a,
+    L: Label + 'a,
+    N: Name + 'a,
+    V: 'a,
 {
-    /// Decode from a string slice.
-    #[must_use]
-    pub fn decode_str(encoded: &str) -> Self {
-        let mut undecoded_prefix = encoded;
-        let mut tags = vec![];
-        while !undecoded_prefix.is_empty() {
-            // Skip trailing whitespace, but stop at the first newline character.
-            let remainder =
-                undecoded_prefix.trim_end_matches(|c: char| c != '\n' && c.is_whitespace());
-            if remainder.is_empty() || remainder.ends_with('\n') {
-                break;
-            }
-            let (next_remainder, next_token) =
-                if let Some((i, _)) = remainder.rmatch_indices(char::is_whitespace).next() {
-                    debug_assert!(i < remainder.len());
-                    // Next token might be preceded by whitespace
-                    (&remainder[..=i], &remainder[i + 1..])
-                } else {
-                    // First token without leading whitespace
-                    ("", remainder)
-                };
-            debug_assert!(!next_token.is_empty());
-            debug_assert_eq!(next_token.trim(), next_token);
-            if let Ok(tag) = Tag::decode_str(next_token) {
-                tags.push(tag);
-                undecoded_prefix = next_remainder;
-            } else {
-                break;
+    let mut index = HashMap::<_, Vec<_>>::new();
+    for tag in tags {
+        let prefix = tag.facet().prefix_without_date_suffix();
+        if prefix.is_empty() {
+            continue;
+        }
+        index.entry(prefix).or_default().push(tag);
+    }
+    index
+}
+
+fn trim_cow_str(input: Cow<'_, str>) -> Cow<'_, str> {
+    match input {
+        Cow::Borrowed(borrowed) => Cow::Borrowed(borrowed.trim()),
+        Cow::Owned(owned) => {
+            let trimmed = owned.trim();
+            if trimmed.len() == owned.len() {
+                Cow::Owned(owned)
+            } else {
+                Cow::Owned(trimmed.to_owned())
             }
         }
-        tags.reverse();
-        if undecoded_prefix.trim().is_empty() {
-            // Discard any preceding whitespace if all tokens have been decoded as tags
-            undecoded_prefix = "";
+    }
+}
+
+pub(crate) fn decode_component(
+    encoded: &[u8],
+    lossy_utf8: bool,
+) -> Result<Cow<'_, str>, DecodeError> {
+    let decoded = percent_decode(encoded);
+    if lossy_utf8 {
+        return Ok(decoded.decode_utf8_lossy());
+    }
+    decoded
+        .decode_utf8()
+        .map_err(Into::into)
+        .map_err(DecodeError::Parse)
+}
+
+/// Decode and validate the encoded components of a tag.
+///
+/// The components are borrowed from the encoded input unless they
+/// contain percent-encoded characters. The validity of the tag as
+/// a whole is not checked.
+pub(crate) fn decode_components<'a>(
+    facet_encoded: &'a str,
+    query: &'a str,
+    fragment: &'a str,
+    options: &DecodeOptions,
+) -> Result<TagRef<'a>, DecodeError> {
+    let DecodeOptions {
+        reject_overencoding,
+        reject_multiple_date_like_suffixes,
+        prop_separator,
+        lossy_utf8,
+        stop_at_newline: _,
+        trim_values: _,
+    } = options;
+    let label_encoded = fragment.as_bytes();
+    if *reject_overencoding && encoding::is_overencoded(label_encoded, encoding::LABEL) {
+        return Err(DecodeError::Parse(anyhow!(
+            "over-encoded label '{fragment}'"
+        )));
+    }
+    let label = decode_component(label_encoded, *lossy_utf8)?;
+    if !label::is_valid(&label) {
+        return Err(DecodeError::Parse(anyhow!("invalid label '{label}'")));
+    }
+    if *reject_overencoding && encoding::is_overencoded(facet_encoded.as_bytes(), encoding::FACET) {
+        return Err(DecodeError::Parse(anyhow!(
+            "over-encoded facet '{facet_encoded}'"
+        )));
+    }
+    let facet = decode_component(facet_encoded.as_bytes(), *lossy_utf8)?;
+    if !facet::is_valid(&facet) {
+        return Err(DecodeError::Parse(anyhow!("invalid facet '{facet}'")));
+    }
+    if facet::has_invalid_date_like_suffix(&facet) {
+        return Err(DecodeError::Parse(anyhow!(
+            "facet with invalid date-like suffix '{facet}'"
+        )));
+    }
+    if *reject_multiple_date_like_suffixes && facet::count_date_like_suffixes(&facet) > 1 {
+        return Err(DecodeError::Parse(anyhow!(
+            "facet with multiple date-like suffixes '{facet}'"
+        )));
+    }
+    let mut props = vec![];
+    if !query.is_empty() {
+        for name_value_encoded in query
+            .as_bytes()
+            .split(|b| char::from(*b) == prop_separator.as_char())
+        {
+            props.push(decode_prop_borrowed(name_value_encoded, options)?);
+        }
+    }
+    Ok(TagRef {
+        label,
+        facet,
+        props,
+    })
+}
+
+pub(crate) fn decode_prop<N, V>(
+    name_value_encoded: &[u8],
+    options: &DecodeOptions,
+) -> Result<Property<N, V>, DecodeError>
+where
+    N: Name,
+    V: Value,
+{
+    decode_prop_borrowed(name_value_encoded, options).map(Property::into_owned)
+}
+
+pub(crate) fn decode_prop_borrowed<'a>(
+    name_value_encoded: &'a [u8],
+    options: &DecodeOptions,
+) -> Result<BorrowedProperty<'a>, DecodeError> {
+    let DecodeOptions {
+        reject_overencoding,
+        reject_multiple_date_like_suffixes: _,
+        prop_separator,
+        lossy_utf8,
+        stop_at_newline: _,
+        trim_values,
+    } = options;
+    let mut name_value_encoded_split = name_value_encoded.split(|b| *b == b'=');
+    let Some(name_encoded) = name_value_encoded_split.next() else {
+        return Err(DecodeError::Parse(anyhow!("missing property name")));
+    };
+    let value_encoded = name_value_encoded_split.next().unwrap_or_default();
+    if name_value_encoded_split.next().is_some() {
+        return Err(DecodeError::Parse(anyhow!(
+            "malformed name=value property '{name_value}'",
+            name_value = percent_decode(name_value_encoded)
+                .decode_utf8()
+                .unwrap_or_default()
+        )));
+    }
+    if *reject_overencoding
+        && encoding::is_overencoded(name_value_encoded, prop_separator.ascii_set())
+    {
+        return Err(DecodeError::Parse(anyhow!(
+            "over-encoded property '{name_value}'",
+            name_value = String::from_utf8_lossy(name_value_encoded)
+        )));
+    }
+    let name = decode_component(name_encoded, *lossy_utf8)?;
+    if name.is_empty() || !props::is_name_valid(&name) {
+        return Err(DecodeError::Parse(anyhow!(
+            "invalid property name '{name}'"
+        )));
+    }
+    let mut value = decode_component(value_encoded, *lossy_utf8)?;
+    if *trim_values {
+        value = trim_cow_str(value);
+    }
+    Ok(Property { name, value })
+}
+
+impl<F, L, N, V> FromStr for Tag<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+    V: Value,
+{
+    type Err = DecodeError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        // This implementation permits leading/trailing whitespace,
+        // other than `Tag::decode_str()` which is more strict.
+        Tag::decode_str(input.trim())
+    }
+}
+
+/// Tags decoded from a text field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTags<F, L, N, V> {
+    /// Valid, decoded tags
+    pub tags: Vec<Tag<F, L, N, V>>,
+
+    /// The remaining, undecoded prefix.
+    pub undecoded_prefix: String,
+}
+
+impl<F, L, N, V> DecodedTags<F, L, N, V> {
+    /// Create from an undecoded prefix and tags.
+    ///
+    /// The tags are neither validated nor reordered. The result is
+    /// equivalent to a freshly decoded value with the same contents.
+    #[must_use]
+    pub fn new(undecoded_prefix: impl Into<String>, tags: Vec<Tag<F, L, N, V>>) -> Self {
+        Self {
+            tags,
+            undecoded_prefix: undecoded_prefix.into(),
+        }
+    }
+
+    /// Return the tag at the given index.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&Tag<F, L, N, V>> {
+        self.tags.get(index)
+    }
+
+    /// Return the tag at the given index for modification.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    #[must_use]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Tag<F, L, N, V>> {
+        self.tags.get_mut(index)
+    }
+
+    /// Remove the tag at the given index.
+    ///
+    /// The order of the remaining tags is preserved.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Option<Tag<F, L, N, V>> {
+        (index < self.tags.len()).then(|| self.tags.remove(index))
+    }
+}
+
+const JOIN_ENCODED_TOKENS_CHAR: char = ' ';
+
+const BOM: char = '\u{feff}';
+
+/// Decode tokens from the end of the encoded input.
+///
+/// Returns the decoded tokens in their original order and the undecoded
+/// prefix, including a leading BOM. Decoding stops at the first token
+/// that could not be decoded.
+pub(crate) fn decode_trailing_tokens<'a, T>(
+    encoded: &'a str,
+    stop_at_newline: bool,
+    mut decode_token: impl FnMut(&'a str) -> Option<T>,
+    mut record_span: impl FnMut(Range<usize>),
+) -> (Vec<T>, &'a str) {
+    // A leading BOM is always preserved as part of the undecoded prefix
+    let (bom, mut undecoded_prefix) = split_bom(encoded);
+    let mut tags = vec![];
+    while !undecoded_prefix.is_empty() {
+        // Skip trailing whitespace, but optionally stop at the first newline character.
+        let remainder = if stop_at_newline {
+            undecoded_prefix.trim_end_matches(|c: char| c != '\n' && c.is_whitespace())
+        } else {
+            undecoded_prefix.trim_end()
+        };
+        if remainder.is_empty() || remainder.ends_with('\n') {
+            break;
+        }
+        let (next_remainder, next_token) =
+            if let Some((i, whitespace)) = remainder.rmatch_indices(char::is_whitespace).next() {
+                debug_assert!(i < remainder.len());
+                // Next token might be preceded by whitespace. The whitespace
+                // character might be encoded by multiple bytes, e.g. U+00A0.
+                remainder.split_at(i + whitespace.len())
+            } else {
+                // First token without leading whitespace
+                ("", remainder)
+            };
+        debug_assert!(!next_token.is_empty());
+        debug_assert_eq!(next_token.trim(), next_token);
+        if let Some(tag) = decode_token(next_token) {
+            tags.push(tag);
+            // Both the remainder and the token are slices of the encoded input
+            record_span(bom.len() + next_remainder.len()..bom.len() + remainder.len());
+            undecoded_prefix = next_remainder;
+        } else {
+            break;
+        }
+    }
+    tags.reverse();
+    if undecoded_prefix.trim().is_empty() {
+        // Discard any preceding whitespace if all tokens have been decoded as tags
+        undecoded_prefix = "";
+    }
+    // Both the BOM and the undecoded prefix are adjacent slices of the encoded input
+    let undecoded_prefix = &encoded[..bom.len() + undecoded_prefix.len()];
+    (tags, undecoded_prefix)
+}
+
+/// Check an encoded token before decoding it.
+pub(crate) fn check_encoded_token(encoded: &str) -> Result<(), DecodeError> {
+    let encoded_trimmed = encoded.trim();
+    if encoded_trimmed != encoded {
+        return Err(DecodeError::Parse(anyhow!(
+            "leading/trailing whitespace in encoded input"
+        )));
+    }
+    if encoded_trimmed.is_empty() {
+        return Err(DecodeError::Parse(anyhow!("empty encoded input")));
+    }
+    if encoded_trimmed.as_bytes().first() == Some(&b'/') {
+        return Err(DecodeError::Parse(anyhow!(
+            "encoded input starts with leading slash `/`"
+        )));
+    }
+    Ok(())
+}
+
+/// Parse an encoded token as an URL relative to the dummy base URL.
+fn parse_encoded_url(encoded: &str) -> Result<Url, DecodeError> {
+    check_encoded_token(encoded)?;
+    let parse_options = Url::options().base_url(Some(dummy_base_url()));
+    parse_options
+        .parse(encoded)
+        .map_err(Into::into)
+        .map_err(DecodeError::Parse)
+}
+
+/// Check if `haystack` contains `needle`, ignoring ASCII case.
+///
+/// Non-ASCII characters must match exactly. Does not allocate.
+fn contains_ignore_ascii_case(haystack: &str, needle: &str) -> bool {
+    let needle = needle.as_bytes();
+    needle.is_empty()
+        || haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle))
+}
+
+/// Split off a leading byte order mark (BOM).
+fn split_bom(encoded: &str) -> (&str, &str) {
+    if encoded.starts_with(BOM) {
+        encoded.split_at(BOM.len_utf8())
+    } else {
+        ("", encoded)
+    }
+}
+
+/// Check if a separator is needed before the first encoded tag.
+///
+/// A separator is needed if the undecoded prefix is not empty and
+/// does not end with a whitespace character. A leading byte order
+/// mark (BOM) is not considered as content.
+fn needs_separator_after_prefix(undecoded_prefix: &str) -> bool {
+    let (_, undecoded_prefix) = split_bom(undecoded_prefix);
+    !undecoded_prefix.is_empty() && undecoded_prefix.trim_end() == undecoded_prefix
+}
+
+/// Normalize whitespace in a text field before decoding.
+///
+/// Converts all CRLF line breaks (`"\r\n"`) into LF (`'\n'`). If
+/// `collapse_spaces` is `true` then runs of ASCII spaces (`' '`) and
+/// tabs (`'\t'`) are collapsed into a single space. All other
+/// characters, including newlines, lone CR characters, and
+/// non-ASCII whitespace, remain untouched.
+///
+/// Returns the input unmodified if no normalization is needed.
+#[must_use]
+pub fn normalize_field_whitespace(input: &str, collapse_spaces: bool) -> Cow<'_, str> {
+    let needs_collapse = collapse_spaces && (input.contains('\t') || input.contains("  "));
+    if !needs_collapse && !input.contains("\r\n") {
+        return Cow::Borrowed(input);
+    }
+    let input = input.replace("\r\n", "\n");
+    if !collapse_spaces {
+        return Cow::Owned(input);
+    }
+    let mut normalized = String::with_capacity(input.len());
+    let mut pending_space = false;
+    for c in input.chars() {
+        if matches!(c, ' ' | '\t') {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            normalized.push(' ');
+            pending_space = false;
+        }
+        normalized.push(c);
+    }
+    if pending_space {
+        normalized.push(' ');
+    }
+    Cow::Owned(normalized)
+}
+
+impl<F, L, N, V> DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+    V: Value,
+{
+    /// Decode from a string slice.
+    #[must_use]
+    pub fn decode_str(encoded: &str) -> Self {
+        Self::decode_str_with_options(encoded, &DecodeOptions::default())
+    }
+
+    /// Decode from a string slice, validating the tags with a policy.
+    ///
+    /// Each token is decoded by [`Tag::decode_str_with_policy()`].
+    #[must_use]
+    pub fn decode_str_with_policy(encoded: &str, policy: &TagPolicy) -> Self {
+        let (tags, undecoded_prefix) = decode_trailing_tokens(
+            encoded,
+            DecodeOptions::default().stop_at_newline,
+            |token| Tag::decode_str_with_policy(token, policy).ok(),
+            |_| (),
+        );
+        Self {
+            tags,
+            undecoded_prefix: undecoded_prefix.to_owned(),
+        }
+    }
+
+    /// Decode from a string slice with custom options.
+    ///
+    /// The options are applied when decoding each tag. Use
+    /// [`DecodeOptions::stop_at_newline`] for decoding tags from
+    /// multiple lines.
+    #[must_use]
+    pub fn decode_str_with_options(encoded: &str, options: &DecodeOptions) -> Self {
+        Self::decode_str_recording_spans(encoded, options, |_| ())
+    }
+
+    /// Return the decoded tags.
+    #[must_use]
+    pub fn tags(&self) -> &[Tag<F, L, N, V>] {
+        &self.tags
+    }
+
+    /// Consume the contents and return only the decoded tags.
+    ///
+    /// The undecoded prefix is discarded.
+    #[must_use]
+    pub fn into_tags(self) -> Vec<Tag<F, L, N, V>> {
+        self.tags
+    }
+
+    /// Decode from a string slice and record the positions of all tags.
+    ///
+    /// Returns the decoded contents together with the byte ranges of the
+    /// encoded tags in `encoded`, in the same order as [`Self::tags`].
+    ///
+    /// The spans are needed for re-encoding with minimal changes,
+    /// see [`Self::encode_into_with_spans()`].
+    #[must_use]
+    pub fn decode_str_with_spans(encoded: &str) -> (Self, Vec<Range<usize>>) {
+        let mut spans = vec![];
+        let decoded =
+            Self::decode_str_recording_spans(encoded, &DecodeOptions::default(), |span| {
+                spans.push(span);
+            });
+        spans.reverse();
+        debug_assert_eq!(decoded.tags.len(), spans.len());
+        (decoded, spans)
+    }
+
+    /// Decode from a string slice and preserve the original token of each tag.
+    ///
+    /// See also: [`DecodedTagsWithSource`]
+    #[must_use]
+    pub fn decode_str_preserving_source(encoded: &str) -> DecodedTagsWithSource<F, L, N, V> {
+        let (decoded, spans) = Self::decode_str_with_spans(encoded);
+        let Self {
+            tags,
+            undecoded_prefix,
+        } = decoded;
+        let tags = tags
+            .into_iter()
+            .zip(spans)
+            .map(|(tag, span)| (tag, encoded[span].to_owned()))
+            .collect();
+        DecodedTagsWithSource {
+            tags,
+            undecoded_prefix,
         }
+    }
+
+    /// Decodes tags backwards and reports their spans in reverse order.
+    fn decode_str_recording_spans(
+        encoded: &str,
+        options: &DecodeOptions,
+        record_span: impl FnMut(Range<usize>),
+    ) -> Self {
+        let (tags, undecoded_prefix) = decode_trailing_tokens(
+            encoded,
+            options.stop_at_newline,
+            |token| Tag::decode_str_with_options(token, options).ok(),
+            record_span,
+        );
         Self {
             tags,
             undecoded_prefix: undecoded_prefix.to_owned(),
@@ -408,17 +2204,15 @@ where
     ///
     /// Adds a space character before the first encoded tag, if the
     /// `undecodedPrefix` is not empty and does not end with a
-    /// whitespace character.
+    /// whitespace character. A leading byte order mark (BOM) in the
+    /// `undecodedPrefix` is ignored.
     ///
     /// # Errors
     ///
     /// Returns an [`fmt::Error`] if writing into the buffer fails.
     pub fn encode_into<W: fmt::Write>(&self, write: &mut W) -> fmt::Result {
         write.write_str(&self.undecoded_prefix)?;
-        // Append a separator before the first encoded tag of the undecoded prefix
-        // is not empty and does not end with a whitespace.
-        let mut append_separator = !self.undecoded_prefix.is_empty()
-            && self.undecoded_prefix.trim_end() == self.undecoded_prefix;
+        let mut append_separator = needs_separator_after_prefix(&self.undecoded_prefix);
         for tag in &self.tags {
             if append_separator {
                 write.write_char(JOIN_ENCODED_TOKENS_CHAR)?;
@@ -429,6 +2223,169 @@ where
         Ok(())
     }
 
+    /// Encode the contents as separate tokens.
+    ///
+    /// Yields the undecoded prefix first, unless it is empty, followed
+    /// by each encoded tag. No separators are included between the
+    /// yielded tokens, i.e. joining them does not reproduce the result
+    /// of [`Self::encode_into()`].
+    pub fn encode_tokens(&self) -> impl Iterator<Item = String> + '_ {
+        let prefix = (!self.undecoded_prefix.is_empty()).then(|| self.undecoded_prefix.clone());
+        prefix.into_iter().chain(self.tags.iter().map(Tag::encode))
+    }
+
+    /// Encode the contents with minimal changes compared to the source.
+    ///
+    /// The `source` and its `spans` must have been obtained from
+    /// [`Self::decode_str_with_spans()`].
+    ///
+    /// The tags are matched with the original tokens in a single forward
+    /// pass. Tags that equal the tag decoded from a subsequent original
+    /// token are copied verbatim from `source`, including any non-canonical
+    /// encoding. Original tokens that are skipped by this matching are
+    /// considered as removed. Tags that do not match any original token
+    /// are re-encoded. A re-encoded tag takes the place of a removed token
+    /// between its matched neighbors, i.e. modifying a tag in place keeps
+    /// the surrounding whitespace.
+    ///
+    /// The whitespace between adjacent original tokens, after the last
+    /// original token, and an unmodified undecoded prefix are preserved.
+    /// All other tags are separated by a single space. If the contents
+    /// have not been modified at all, the output is byte-identical to
+    /// `source`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`fmt::Error`] if writing into the buffer fails.
+    pub fn encode_into_with_spans<W: fmt::Write>(
+        &self,
+        source: &str,
+        spans: &[Range<usize>],
+        write: &mut W,
+    ) -> fmt::Result {
+        let source_prefix = spans.first().map_or(source, |span| &source[..span.start]);
+        let (source_bom, source_prefix_without_bom) = split_bom(source_prefix);
+        let source_prefix_unmodified = if source_prefix_without_bom.trim().is_empty() {
+            self.undecoded_prefix == source_bom
+        } else {
+            self.undecoded_prefix == source_prefix
+        };
+        let prefix = if source_prefix_unmodified {
+            source_prefix
+        } else {
+            &self.undecoded_prefix
+        };
+        write.write_str(prefix)?;
+        let mut append_separator = needs_separator_after_prefix(prefix);
+        let mut prev_span_index = None;
+        for (tag, slot) in self.tags.iter().zip(self.match_spans(source, spans)) {
+            let adjacent_span_index = slot
+                .map(|(span_index, _)| span_index)
+                .filter(|&span_index| span_index > 0 && prev_span_index == Some(span_index - 1));
+            if let Some(span_index) = adjacent_span_index {
+                // Preserve the original whitespace between adjacent tokens
+                write.write_str(&source[spans[span_index - 1].end..spans[span_index].start])?;
+            } else if append_separator {
+                write.write_char(JOIN_ENCODED_TOKENS_CHAR)?;
+            }
+            match slot {
+                Some((span_index, true)) => {
+                    write.write_str(&source[spans[span_index].clone()])?;
+                }
+                _ => {
+                    tag.encode_into(write)?;
+                }
+            }
+            prev_span_index = slot.map(|(span_index, _)| span_index);
+            append_separator = true;
+        }
+        if let Some(last_span) = prev_span_index
+            .filter(|span_index| span_index + 1 == spans.len())
+            .map(|span_index| &spans[span_index])
+        {
+            // Preserve the original trailing whitespace
+            write.write_str(&source[last_span.end..])?;
+        }
+        Ok(())
+    }
+
+    /// Match the tags with the original tokens in `source`.
+    ///
+    /// Returns the index of the span for each tag and if the tag equals
+    /// the tag decoded from the original token. Unmatched tags are
+    /// assigned to a skipped span between their matched neighbors if
+    /// available.
+    fn match_spans(&self, source: &str, spans: &[Range<usize>]) -> Vec<Option<(usize, bool)>> {
+        let decoded_spans = spans
+            .iter()
+            .map(|span| Tag::<F, L, N, V>::decode_str(&source[span.clone()]).ok())
+            .collect::<Vec<_>>();
+        let mut next_span_index = 0;
+        let mut slots = self
+            .tags
+            .iter()
+            .map(|tag| {
+                let span_index = decoded_spans[next_span_index..]
+                    .iter()
+                    .position(|decoded| decoded.as_ref() == Some(tag))
+                    .map(|offset| next_span_index + offset)?;
+                next_span_index = span_index + 1;
+                Some((span_index, true))
+            })
+            .collect::<Vec<_>>();
+        let mut next_span_index = 0;
+        for i in 0..slots.len() {
+            if let Some((span_index, _)) = slots[i] {
+                next_span_index = span_index + 1;
+                continue;
+            }
+            let next_matched_span_index = slots[i + 1..]
+                .iter()
+                .find_map(|slot| slot.map(|(span_index, _)| span_index))
+                .unwrap_or(spans.len());
+            if next_span_index < next_matched_span_index {
+                slots[i] = Some((next_span_index, false));
+                next_span_index += 1;
+            }
+        }
+        slots
+    }
+
+    /// Encode the contents with minimal changes compared to the original.
+    ///
+    /// The `original` is the string from which the contents have been
+    /// decoded. It is tokenized again to find the spans of the original
+    /// tags, i.e. no spans need to be stored when decoding.
+    ///
+    /// Tags that decode identically from an original token are copied
+    /// verbatim. All other tags are re-encoded, see
+    /// [`Self::encode_into_with_spans()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`fmt::Error`] if writing into the buffer fails.
+    pub fn encode_into_based_on<W: fmt::Write>(
+        &self,
+        original: &str,
+        write: &mut W,
+    ) -> fmt::Result {
+        let (_, spans) = Self::decode_str_with_spans(original);
+        self.encode_into_with_spans(original, &spans, write)
+    }
+
+    /// Encode the contents into an I/O sink.
+    ///
+    /// Streams the undecoded prefix and the encoded tags into the sink
+    /// without buffering the whole field in memory. The formatting is
+    /// the same as for [`Self::encode_into()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] if writing into the sink fails.
+    pub fn write_to<W: io::Write>(&self, write: &mut W) -> io::Result<()> {
+        write_fmt_to_io(write, |adapter| self.encode_into(adapter))
+    }
+
     /// Re-encode the contents.
     ///
     /// # Errors
@@ -436,9 +2393,7 @@ where
     /// Returns an [`fmt::Error`] if writing into the buffer fails.
     pub fn reencode(self) -> Result<String, fmt::Error> {
         let mut reencoded = self.undecoded_prefix;
-        // Append a separated before the first encoded tag of the undecoded prefix
-        // is not empty and does not end with a whitespace.
-        let mut append_separator = !reencoded.is_empty() && reencoded.trim_end() == reencoded;
+        let mut append_separator = needs_separator_after_prefix(&reencoded);
         for tag in &self.tags {
             if append_separator {
                 reencoded.push(JOIN_ENCODED_TOKENS_CHAR);
@@ -449,74 +2404,622 @@ where
         Ok(reencoded)
     }
 
-    /// Reorder and deduplicate tags.
+    /// Re-encode the contents within a maximum number of bytes.
     ///
-    /// Canonical ordering:
-    ///   1. Tags without a facet
-    ///   2. Tags with a non-date-like facet
-    ///   3. Tags with a date-like facet (by descending suffix)
+    /// Drops whole tags in the order defined by `policy` until the
+    /// re-encoded contents fit into `max_bytes`. The remaining tags are
+    /// re-encoded in their current order. The undecoded prefix is always
+    /// preserved.
     ///
-    /// Within each group tags are sorted by facet, then by label. For tags with
-    /// equal facets those with a label are sorted before those without a label.
+    /// Returns `None` if the undecoded prefix alone exceeds `max_bytes`.
+    #[must_use]
+    pub fn reencode_within(mut self, max_bytes: usize, policy: TruncatePolicy) -> Option<String> {
+        if self.undecoded_prefix.len() > max_bytes {
+            return None;
+        }
+        let leading_separator_len =
+            usize::from(needs_separator_after_prefix(&self.undecoded_prefix));
+        let encoded_lens = self
+            .tags
+            .iter()
+            .map(|tag| tag.encode().len())
+            .collect::<Vec<_>>();
+        let reencoded_len = |keep: &[bool]| {
+            let (count, len) = encoded_lens
+                .iter()
+                .zip(keep)
+                .filter(|(_, keep)| **keep)
+                .fold((0, 0), |(count, len), (encoded_len, _)| {
+                    (count + 1, len + encoded_len)
+                });
+            let separators_len = if count > 0 {
+                leading_separator_len + count - 1
+            } else {
+                0
+            };
+            self.undecoded_prefix.len() + len + separators_len
+        };
+        let mut drop_order = (0..self.tags.len()).collect::<Vec<_>>();
+        match policy {
+            TruncatePolicy::DropTail => {
+                drop_order.reverse();
+            }
+            TruncatePolicy::DropOldestFirst => {
+                // Undated tags are dropped last in reverse order.
+                drop_order.sort_by(|&lhs, &rhs| {
+                    match (
+                        self.tags[lhs].facet().analyze().1,
+                        self.tags[rhs].facet().analyze().1,
+                    ) {
+                        (Some(lhs_suffix), Some(rhs_suffix)) => lhs_suffix.cmp(rhs_suffix),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => rhs.cmp(&lhs),
+                    }
+                });
+            }
+        }
+        let mut keep = vec![true; self.tags.len()];
+        for index in drop_order {
+            if reencoded_len(&keep) <= max_bytes {
+                break;
+            }
+            keep[index] = false;
+        }
+        let mut keep = keep.into_iter();
+        self.tags.retain(|_| keep.next().unwrap_or_default());
+        let reencoded = self.reencode().ok()?;
+        debug_assert!(reencoded.len() <= max_bytes);
+        Some(reencoded)
+    }
+
+    /// Replace all tags while preserving the undecoded prefix.
     ///
-    /// Tags with a date-like facet are sorted in descending order by their
-    /// date-like suffix, i.e. newer dates are sorted before older dates.
-    #[allow(clippy::missing_panics_doc)]
+    /// The new tags are neither reordered nor deduplicated. Invoke
+    /// [`Self::reorder_and_dedup()`] afterwards if needed.
+    pub fn replace_tags(&mut self, tags: impl IntoIterator<Item = Tag<F, L, N, V>>) {
+        self.tags.clear();
+        self.tags.extend(tags);
+    }
+
+    /// Reorder and deduplicate tags.
+    ///
+    /// Sorts the tags into canonical order, see [`Tag::cmp_canonical()`],
+    /// and removes consecutive duplicates afterwards.
     pub fn reorder_and_dedup(&mut self) {
+        // Skip sorting if the tags are already in canonical order,
+        // which is the common case when re-saving unmodified tags.
+        if !self.is_sorted_canonically() {
+            self.sort_by_policy(SortPolicy::Canonical);
+        }
+        self.tags.dedup();
+    }
+
+    fn is_sorted_canonically(&self) -> bool {
+        self.all_adjacent_tags_ordered(|ordering| ordering != Ordering::Greater)
+    }
+
+    /// Check the canonical ordering of all pairs of adjacent tags.
+    fn all_adjacent_tags_ordered(&self, mut accept: impl FnMut(Ordering) -> bool) -> bool {
+        self.tags
+            .iter()
+            .zip(self.tags.iter().skip(1))
+            .all(|(lhs, rhs)| accept(lhs.cmp_canonical(rhs)))
+    }
+
+    /// Check if the tags are already in canonical order without duplicates.
+    ///
+    /// Returns `true` if [`Self::reorder_and_dedup()`] would not
+    /// modify the tags.
+    #[must_use]
+    pub fn is_already_canonical(&self) -> bool {
+        self.all_adjacent_tags_ordered(|ordering| ordering == Ordering::Less)
+    }
+
+    /// Reorder and deduplicate tags with custom options.
+    ///
+    /// See also: [`Self::reorder_and_dedup()`], [`Tag::cmp_canonical_with_options()`]
+    pub fn reorder_and_dedup_with_options(&mut self, options: &DedupOptions) {
+        self.tags
+            .sort_by(|lhs, rhs| lhs.cmp_canonical_with_options(rhs, options));
+        self.tags.dedup();
+    }
+
+    /// Canonicalize, reorder, and deduplicate tags.
+    ///
+    /// Canonicalizes the properties of all tags before reordering
+    /// and deduplicating them. Tags that only differ by the order of
+    /// their properties are collapsed into a single tag.
+    ///
+    /// See also: [`Self::canonicalize_all()`], [`Self::reorder_and_dedup()`]
+    pub fn reorder_dedup_and_canonicalize(&mut self) {
+        self.canonicalize_all();
+        self.reorder_and_dedup();
+    }
+
+    /// Apply a fallible transformation to each tag in place.
+    ///
+    /// Stops at the first error. Tags that have already been
+    /// transformed remain modified.
+    ///
+    /// The tags are neither reordered nor deduplicated afterwards.
+    /// Invoke [`Self::reorder_and_dedup()`] if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of `f`.
+    pub fn try_map_tags<E>(
+        &mut self,
+        f: impl FnMut(&mut Tag<F, L, N, V>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.tags.iter_mut().try_for_each(f)
+    }
+
+    /// Sort the tags according to the given policy.
+    ///
+    /// The sort is stable, i.e. tags that compare equal keep their
+    /// relative order. Duplicates are not removed.
+    pub fn sort_by_policy(&mut self, policy: SortPolicy) {
+        self.tags.sort_by(|lhs, rhs| lhs.cmp_by(rhs, policy));
+    }
+
+    /// Sort the tags by a custom order of facet prefixes.
+    ///
+    /// Tags are sorted by the position of their facet prefix, i.e. the
+    /// facet without its date-like suffix, in `order`. Tags with facet
+    /// prefixes that are not listed are sorted after all listed ones
+    /// in ascending order of their prefixes. This includes tags without
+    /// a facet that have an empty prefix.
+    ///
+    /// Tags with the same facet prefix are sorted in canonical order,
+    /// see [`Tag::cmp_canonical()`].
+    ///
+    /// The sort is stable. Duplicates are not removed.
+    pub fn sort_with_facet_order(&mut self, order: &[&str]) {
+        let rank = |prefix: &str| {
+            order
+                .iter()
+                .position(|ordered| *ordered == prefix)
+                .unwrap_or(order.len())
+        };
         self.tags.sort_by(|lhs, rhs| {
-            if rhs.facet().has_date_like_suffix() {
-                if lhs.facet().has_date_like_suffix() {
-                    // Using unwrap() is safe after we already checked that
-                    // the contents of both facets match the date-like format.
-                    let (_, lhs_suffix) = lhs
-                        .facet()
-                        .try_split_into_prefix_and_date_like_suffix()
-                        .unwrap();
-                    let (_, rhs_suffix) = rhs
-                        .facet()
-                        .try_split_into_prefix_and_date_like_suffix()
-                        .unwrap();
-                    // Descending order by decimal digits encoded as ASCII chars
-                    let ordering = rhs_suffix.cmp(lhs_suffix);
-                    if ordering != Ordering::Equal {
-                        return ordering;
-                    }
-                } else {
-                    return Ordering::Less;
-                }
-            } else if lhs.facet().has_date_like_suffix() {
-                return Ordering::Greater;
+            let lhs_prefix = lhs.facet().prefix_without_date_suffix();
+            let rhs_prefix = rhs.facet().prefix_without_date_suffix();
+            rank(lhs_prefix)
+                .cmp(&rank(rhs_prefix))
+                .then_with(|| lhs_prefix.cmp(rhs_prefix))
+                .then_with(|| lhs.cmp_canonical(rhs))
+        });
+    }
+
+    /// Iterate over all tags together with their facet prefix and date.
+    ///
+    /// The prefix is the facet without its date-like suffix or the whole
+    /// facet if it has no date-like suffix. The date is `None` if the facet
+    /// has no date-like suffix or if the suffix does not encode a valid date.
+    ///
+    /// See also: [`Tag::facet_prefix_and_date()`]
+    pub fn enumerate_with_date(
+        &self,
+    ) -> impl Iterator<Item = (&str, Option<Date>, &Tag<F, L, N, V>)> {
+        self.tags.iter().map(|tag| {
+            let (prefix, date) = tag.facet_prefix_and_date();
+            (prefix, date, tag)
+        })
+    }
+
+    /// Group tags by the date of their facet.
+    ///
+    /// Tags without a date-like facet or with a date-like suffix
+    /// that does not encode a valid date are grouped under `None`.
+    ///
+    /// The keys are sorted in ascending order with `None` first,
+    /// followed by all dates from oldest to newest. Tags within each
+    /// group appear in the same order as in [`Self::tags`].
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    pub fn group_by_date(&self) -> BTreeMap<Option<Date>, Vec<&Tag<F, L, N, V>>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for tag in &self.tags {
+            let (_, date) = tag.facet_prefix_and_date();
+            groups.entry(date).or_default().push(tag);
+        }
+        groups
+    }
+
+    /// Iterate over all tags that are older than the given number of days.
+    ///
+    /// A tag is older if its date is more than `days` days before
+    /// `reference`. Undated tags without a date-like suffix or with
+    /// an invalid date are excluded. The tags are yielded in order.
+    ///
+    /// Nothing is yielded if the threshold date is out of range.
+    pub fn tags_older_than(
+        &self,
+        reference: Date,
+        days: i64,
+    ) -> impl Iterator<Item = &Tag<F, L, N, V>> {
+        let threshold = days
+            .checked_mul(86_400)
+            .map(time::Duration::seconds)
+            .and_then(|duration| reference.checked_sub(duration));
+        self.tags.iter().filter(move |tag| {
+            let (_, date) = tag.facet_prefix_and_date();
+            date.zip(threshold)
+                .is_some_and(|(date, threshold)| date < threshold)
+        })
+    }
+
+    /// Keep only the latest dated tag per facet prefix and label.
+    ///
+    /// Tags with a valid date are grouped by their facet prefix, i.e.
+    /// the facet without the date-like suffix, and their label. The
+    /// properties are ignored. Only the tags with the latest date are
+    /// kept within each group, all older tags are removed. Multiple
+    /// tags with the same latest date are all kept.
+    ///
+    /// Undated tags without a date-like suffix or with an invalid date
+    /// are always kept. The order of the remaining tags is preserved.
+    ///
+    /// Returns the number of removed tags.
+    pub fn retain_latest_per_facet_prefix(&mut self) -> usize {
+        let mut latest = HashMap::<_, Date>::new();
+        for tag in &self.tags {
+            let (prefix, date) = tag.facet_prefix_and_date();
+            let Some(date) = date else {
+                continue;
+            };
+            latest
+                .entry((prefix, tag.label().as_ref()))
+                .and_modify(|latest| *latest = (*latest).max(date))
+                .or_insert(date);
+        }
+        let keep = self
+            .tags
+            .iter()
+            .map(|tag| {
+                let (prefix, date) = tag.facet_prefix_and_date();
+                date.map_or(true, |date| latest[&(prefix, tag.label().as_ref())] == date)
+            })
+            .collect::<Vec<_>>();
+        let len_before = self.tags.len();
+        let mut keep = keep.into_iter();
+        self.tags.retain(|_| keep.next().unwrap_or(true));
+        len_before - self.tags.len()
+    }
+
+    /// Flatten all tags into rows, e.g. for exporting into a table.
+    ///
+    /// Emits one row per property. Tags without properties result
+    /// in a single row without a property name and value. The rows
+    /// are ordered by tag and then by property.
+    #[must_use]
+    pub fn to_rows(&self) -> Vec<TagRow<'_>> {
+        let mut rows = Vec::with_capacity(self.tags.len());
+        for tag in &self.tags {
+            let facet = tag.facet().as_ref();
+            let label = tag.label().as_ref();
+            if !tag.has_props() {
+                rows.push(TagRow {
+                    facet,
+                    label,
+                    prop_name: None,
+                    prop_value: None,
+                });
+                continue;
             }
-            if rhs.has_facet() {
-                if lhs.has_facet() {
-                    let ordering = lhs.facet().cmp(rhs.facet());
-                    if ordering != Ordering::Equal {
-                        return ordering;
-                    }
-                } else {
-                    return Ordering::Less;
-                }
-            } else if lhs.has_facet() {
-                return Ordering::Greater;
+            rows.extend(tag.props().iter().map(|prop| TagRow {
+                facet,
+                label,
+                prop_name: Some(prop.name().as_ref()),
+                prop_value: Some(prop.value().as_ref()),
+            }));
+        }
+        rows
+    }
+
+    /// Collect the distinct facet prefixes of all tags.
+    ///
+    /// The prefix of a facet is the facet without its date-like suffix,
+    /// see [`facet::prefix_without_date_suffix()`]. Empty prefixes of
+    /// tags without a facet or with a date-only facet are omitted.
+    ///
+    /// The prefixes are sorted in ascending order.
+    #[must_use]
+    pub fn distinct_facet_prefixes(&self) -> Vec<&str> {
+        let mut prefixes = self
+            .tags
+            .iter()
+            .map(|tag| tag.facet().prefix_without_date_suffix())
+            .filter(|prefix| !prefix.is_empty())
+            .collect::<Vec<_>>();
+        prefixes.sort_unstable();
+        prefixes.dedup();
+        prefixes
+    }
+
+    /// Canonicalize the properties of all tags.
+    ///
+    /// Invokes [`Tag::canonicalize()`] for each tag. The order of
+    /// the tags is not modified.
+    ///
+    /// Fields that contain the same tags re-encode identically after
+    /// invoking both this function and [`Self::reorder_and_dedup()`],
+    /// independent of the initial order of tags and properties.
+    pub fn canonicalize_all(&mut self) {
+        for tag in &mut self.tags {
+            tag.canonicalize();
+        }
+    }
+
+    /// Find the index of the first tag that matches a predicate.
+    #[must_use]
+    pub fn position(&self, predicate: impl Fn(&Tag<F, L, N, V>) -> bool) -> Option<usize> {
+        self.tags.iter().position(predicate)
+    }
+
+    /// Find the index of the first tag with the given facet and label.
+    ///
+    /// See also: [`Tag::identity_key()`]
+    #[must_use]
+    pub fn index_of_identity(&self, facet: &str, label: &str) -> Option<usize> {
+        self.position(|tag| tag.identity_key() == (facet, label))
+    }
+
+    /// Search for tags that contain a query string.
+    ///
+    /// Returns all tags with a facet, label, property name, or property
+    /// value that contains `query`, ignoring ASCII case. Non-ASCII
+    /// characters must match exactly. The tags are returned in order.
+    ///
+    /// The decoded components are searched, i.e. a query with a space
+    /// character matches a label with a space character that is encoded
+    /// as `%20`. Use [`Self::search_encoded()`] for searching the encoded
+    /// tags.
+    ///
+    /// An empty `query` matches all tags.
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a Tag<F, L, N, V>> {
+        let matches = move |component: &str| contains_ignore_ascii_case(component, query);
+        self.tags.iter().filter(move |tag| {
+            matches(tag.facet().as_ref())
+                || matches(tag.label().as_ref())
+                || tag
+                    .props()
+                    .iter()
+                    .any(|prop| matches(prop.name().as_ref()) || matches(prop.value().as_ref()))
+        })
+    }
+
+    /// Search for encoded tags that contain a query string.
+    ///
+    /// Returns all tags with an encoded form that contains `query`,
+    /// ignoring ASCII case. Percent-encoded characters are only matched
+    /// by their escape sequence, e.g. a space character by `%20`. The
+    /// tags are returned in order.
+    ///
+    /// In contrast to [`Self::search()`] the query may also match the
+    /// separators between components like `#`, `?`, or `&`.
+    ///
+    /// An empty `query` matches all tags.
+    pub fn search_encoded<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> impl Iterator<Item = &'a Tag<F, L, N, V>> {
+        self.tags
+            .iter()
+            .filter(move |tag| contains_ignore_ascii_case(&tag.encode(), query))
+    }
+
+    /// Check all tags for potential problems.
+    ///
+    /// Collects advisory findings that do not render the tags invalid,
+    /// but might be fixed before re-encoding them. The findings are
+    /// ordered by tag index.
+    #[must_use]
+    pub fn lint(&self) -> Vec<TagLint<'_>> {
+        let mut lints = Vec::new();
+        for (index, tag) in self.tags.iter().enumerate() {
+            if let Some(original_index) = self.tags[..index].iter().position(|other| other == tag) {
+                lints.push(TagLint {
+                    index,
+                    kind: TagLintKind::DuplicateOf(original_index),
+                });
             }
-            debug_assert_eq!(lhs.facet(), rhs.facet());
-            // Tags with labels before tags without labels
-            debug_assert_eq!(lhs.facet(), rhs.facet());
-            if rhs.has_label() {
-                if lhs.has_label() {
-                    lhs.label().cmp(rhs.label())
-                } else {
-                    Ordering::Greater
+            if tag.facet().has_date_like_suffix() && tag.facet_prefix_and_date().1.is_none() {
+                lints.push(TagLint {
+                    index,
+                    kind: TagLintKind::InvalidDateSuffix,
+                });
+            }
+            for prop in tag.props() {
+                if prop.is_empty_value() {
+                    lints.push(TagLint {
+                        index,
+                        kind: TagLintKind::EmptyPropValue(prop.name().as_ref()),
+                    });
                 }
-            } else if lhs.has_label() {
-                Ordering::Less
-            } else {
-                Ordering::Equal
             }
-        });
-        self.tags.dedup();
+            if tag
+                .props()
+                .windows(2)
+                .any(|pair| pair[0].cmp_canonical(&pair[1]) == Ordering::Greater)
+            {
+                lints.push(TagLint {
+                    index,
+                    kind: TagLintKind::NonCanonicalPropOrder,
+                });
+            }
+        }
+        lints
+    }
+}
+
+/// Tags decoded from a text field together with their original tokens
+///
+/// See also: [`DecodedTags::decode_str_preserving_source()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTagsWithSource<F, L, N, V> {
+    /// Valid, decoded tags paired with the original, encoded tokens
+    pub tags: Vec<(Tag<F, L, N, V>, String)>,
+
+    /// The remaining, undecoded prefix.
+    pub undecoded_prefix: String,
+}
+
+impl<F, L, N, V> DecodedTagsWithSource<F, L, N, V> {
+    /// Discard the original tokens.
+    #[must_use]
+    pub fn into_decoded_tags(self) -> DecodedTags<F, L, N, V> {
+        let Self {
+            tags,
+            undecoded_prefix,
+        } = self;
+        DecodedTags {
+            tags: tags.into_iter().map(|(tag, _)| tag).collect(),
+            undecoded_prefix,
+        }
+    }
+}
+
+impl<F, L, N, V> FromStr for DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+    V: Value,
+{
+    type Err = Infallible;
+
+    /// Delegates to [`DecodedTags::decode_str()`] which never fails.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(Self::decode_str(input))
+    }
+}
+
+impl<F, L, N, V> Extend<Tag<F, L, N, V>> for DecodedTags<F, L, N, V> {
+    /// Append tags without reordering or deduplicating them.
+    fn extend<T: IntoIterator<Item = Tag<F, L, N, V>>>(&mut self, iter: T) {
+        self.tags.extend(iter);
+    }
+}
+
+impl<'a, F, L, N, V> Extend<&'a Tag<F, L, N, V>> for DecodedTags<F, L, N, V>
+where
+    Tag<F, L, N, V>: Clone,
+{
+    /// Append clones of tags without reordering or deduplicating them.
+    fn extend<T: IntoIterator<Item = &'a Tag<F, L, N, V>>>(&mut self, iter: T) {
+        self.tags.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<F, L, N, V> PartialOrd for Tag<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name + Eq,
+    V: AsRef<str> + Eq,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F, L, N, V> Ord for Tag<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name + Eq,
+    V: AsRef<str> + Eq,
+{
+    /// Delegates to [`Tag::cmp_canonical()`].
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_canonical(other)
+    }
+}
+
+impl<F, L, N, V> PartialOrd for DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name + Eq,
+    V: AsRef<str> + Eq,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
+impl<F, L, N, V> Ord for DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name + Eq,
+    V: AsRef<str> + Eq,
+{
+    /// Compares the undecoded prefix first and then the tags
+    /// lexicographically in canonical order, see [`Tag::cmp_canonical()`].
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.undecoded_prefix
+            .cmp(&other.undecoded_prefix)
+            .then_with(|| self.tags.cmp(&other.tags))
+    }
+}
+
+impl<F, L, N, V> From<Tag<F, L, N, V>> for DecodedTags<F, L, N, V> {
+    /// Wrap a single tag with an empty undecoded prefix.
+    fn from(tag: Tag<F, L, N, V>) -> Self {
+        Self::new(String::new(), vec![tag])
+    }
+}
+
+/// A flattened row of a tag with at most one property
+///
+/// See also: [`DecodedTags::to_rows()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagRow<'a> {
+    /// The facet, might be empty.
+    pub facet: &'a str,
+
+    /// The label, might be empty.
+    pub label: &'a str,
+
+    /// The property name, `None` for tags without properties.
+    pub prop_name: Option<&'a str>,
+
+    /// The property value, `None` for tags without properties.
+    pub prop_value: Option<&'a str>,
+}
+
+/// A finding of [`DecodedTags::lint()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagLint<'a> {
+    /// The index of the affected tag in [`DecodedTags::tags`].
+    pub index: usize,
+
+    /// The kind of finding.
+    pub kind: TagLintKind<'a>,
+}
+
+/// Kinds of [`TagLint`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagLintKind<'a> {
+    /// Duplicate of the tag at the given, preceding index.
+    ///
+    /// Would be removed by [`DecodedTags::reorder_and_dedup()`].
+    DuplicateOf(usize),
+
+    /// Date-like suffix of the facet that does not encode a valid date.
+    InvalidDateSuffix,
+
+    /// Property with the given name and an empty value.
+    EmptyPropValue(&'a str),
+
+    /// Properties that are not in canonical order.
+    ///
+    /// Would be reordered by [`Tag::canonicalize()`].
+    NonCanonicalPropOrder,
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,70 @@
+// SPDX-FileCopyrightText: The gigtag authors
+// SPDX-License-Identifier: MPL-2.0
+
+//! Unicode normalization of labels and facets
+//!
+//! Canonically equivalent strings might differ byte-wise, e.g. a
+//! precomposed `é` (U+00E9) and `e` followed by a combining acute
+//! accent (U+0301). Normalizing them into Normalization Form C (NFC)
+//! ensures that such labels and facets compare equal.
+//!
+//! Normalization is opt-in, because it breaks the byte-exact
+//! round-trip of decoding and re-encoding.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization as _};
+
+use crate::{DecodedTags, Facet, Label, Name, Tag};
+
+/// Normalize a string into Normalization Form C (NFC).
+///
+/// Returns the input unmodified if it is already normalized.
+#[must_use]
+pub fn nfc(input: &str) -> Cow<'_, str> {
+    if is_nfc_quick(input.chars()) == IsNormalized::Yes {
+        return Cow::Borrowed(input);
+    }
+    let normalized = input.nfc().collect::<String>();
+    if normalized == input {
+        Cow::Borrowed(input)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+impl<F, L, N, V> Tag<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    /// Normalize the label and the facet into Normalization Form C (NFC).
+    ///
+    /// Properties are not modified.
+    pub fn normalize_nfc(&mut self) {
+        if let Cow::Owned(label) = nfc(self.label.as_ref()) {
+            self.label = L::from_string(label);
+        }
+        if let Cow::Owned(facet) = nfc(self.facet.as_ref()) {
+            self.facet = F::from_string(facet);
+        }
+        self.debug_assert_valid();
+    }
+}
+
+impl<F, L, N, V> DecodedTags<F, L, N, V>
+where
+    F: Facet,
+    L: Label,
+    N: Name,
+{
+    /// Normalize the labels and facets of all tags into Normalization Form C (NFC).
+    ///
+    /// The tags are neither reordered nor deduplicated. Invoke
+    /// [`Self::reorder_and_dedup()`] afterwards to remove tags that
+    /// only differed by their normalization.
+    pub fn normalize_nfc(&mut self) {
+        self.tags.iter_mut().for_each(Tag::normalize_nfc);
+    }
+}
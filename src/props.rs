@@ -3,9 +3,13 @@
 
 //! Named properties
 
-use std::{borrow::Cow, fmt, ops::Deref};
+use std::{borrow::Cow, cmp::Ordering, collections::BTreeMap, fmt, ops::Deref, str::FromStr};
 
+use anyhow::anyhow;
 use compact_str::{CompactString, ToCompactString as _};
+use percent_encoding::{percent_encode, AsciiSet};
+
+use crate::{encoding, DecodeError, DecodeOptions};
 
 /// Check if the given name is valid.
 ///
@@ -22,6 +26,64 @@ pub fn is_name_empty(name: &str) -> bool {
     name.is_empty()
 }
 
+/// Build properties from name/value pairs.
+///
+/// The properties are created in the given order. Duplicate names
+/// are permitted.
+///
+/// # Errors
+///
+/// Returns a [`DecodeError`] if a name is empty or invalid,
+/// see [`is_name_valid()`].
+pub fn props_from_pairs<N, V, K, U>(
+    pairs: impl IntoIterator<Item = (K, U)>,
+) -> Result<Vec<Property<N, V>>, DecodeError>
+where
+    N: Name,
+    V: Value,
+    K: AsRef<str>,
+    U: AsRef<str>,
+{
+    pairs
+        .into_iter()
+        .map(|(name, value)| {
+            let name = name.as_ref();
+            if name.is_empty() || !is_name_valid(name) {
+                return Err(DecodeError::Parse(anyhow!(
+                    "invalid property name '{name}'"
+                )));
+            }
+            Ok(Property {
+                name: N::from_str(name),
+                value: V::from_str(value.as_ref()),
+            })
+        })
+        .collect()
+}
+
+/// Collect properties into a map with unique names.
+///
+/// If multiple properties have the same name, then the last
+/// property wins.
+#[must_use]
+pub fn into_btree_map<N, V>(props: Vec<Property<N, V>>) -> BTreeMap<N, V>
+where
+    N: Ord,
+{
+    props
+        .into_iter()
+        .map(|Property { name, value }| (name, value))
+        .collect()
+}
+
+/// Create properties from a map, ordered by name.
+#[must_use]
+pub fn props_from_btree_map<N, V>(map: BTreeMap<N, V>) -> Vec<Property<N, V>> {
+    map.into_iter()
+        .map(|(name, value)| Property { name, value })
+        .collect()
+}
+
 /// Common trait for names
 pub trait Name: AsRef<str> + fmt::Debug + Default + PartialEq + Sized {
     /// Create a name from a borrowed string slice.
@@ -43,6 +105,9 @@ pub trait Name: AsRef<str> + fmt::Debug + Default + PartialEq + Sized {
     /// Create a name from a copy-on-write string.
     ///
     /// The argument must be a valid name.
+    ///
+    /// Implementations should take ownership of an owned string
+    /// without copying it.
     #[must_use]
     fn from_cow_str(name: Cow<'_, str>) -> Self;
 
@@ -203,12 +268,47 @@ pub trait Value: AsRef<str> + Default + PartialEq + Sized {
     /// Create a value from a copy-on-write string.
     ///
     /// The argument must be a valid value.
+    ///
+    /// Implementations should take ownership of an owned string
+    /// without copying it.
     #[must_use]
     fn from_cow_str(value: Cow<'_, str>) -> Self;
 
     /// Create a value from a precompiled format string.
     #[must_use]
     fn from_format_args(format_args: fmt::Arguments<'_>) -> Self;
+
+    /// Parse the value.
+    ///
+    /// The value is parsed verbatim, i.e. without trimming whitespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns the parse error of `T` if the value could not be parsed.
+    fn parse<T: FromStr>(&self) -> Result<T, T::Err> {
+        self.as_ref().parse()
+    }
+
+    /// Interpret the value as a boolean.
+    ///
+    /// Accepts `true`/`1` for `true` and `false`/`0` for `false`.
+    /// Returns `None` for all other values.
+    #[must_use]
+    fn as_bool(&self) -> Option<bool> {
+        match self.as_ref() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Interpret the value as a floating-point number.
+    ///
+    /// Returns `None` if the value could not be parsed.
+    #[must_use]
+    fn as_f64(&self) -> Option<f64> {
+        self.parse().ok()
+    }
 }
 
 impl Value for String {
@@ -247,8 +347,44 @@ impl Value for CompactString {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Separator between encoded properties
+///
+/// Use [`PropSeparator::Semicolon`] only for storing tags in fields
+/// that do not permit the `&` character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PropSeparator {
+    /// The standard `&` separator.
+    #[default]
+    Ampersand,
+
+    /// The alternative `;` separator.
+    ///
+    /// Occurrences of `;` in names and values are percent-encoded.
+    Semicolon,
+}
+
+impl PropSeparator {
+    /// The separator character.
+    #[must_use]
+    pub const fn as_char(self) -> char {
+        match self {
+            Self::Ampersand => '&',
+            Self::Semicolon => ';',
+        }
+    }
+
+    pub(crate) const fn ascii_set(self) -> &'static AsciiSet {
+        match self {
+            Self::Ampersand => encoding::PROPS,
+            Self::Semicolon => encoding::PROPS_SEMICOLON_SEPARATED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// A named property
+///
+/// Properties are ordered by name and then by value.
 pub struct Property<N, V> {
     /// The name
     pub name: N,
@@ -281,11 +417,150 @@ where
     }
 
     /// Check if the property is valid.
+    ///
+    /// A valid property has a valid, non-empty name.
     #[must_use]
     pub fn is_valid(&self) -> bool {
-        self.has_name()
+        self.name.is_valid() && self.has_name()
+    }
+}
+
+impl<N, V> Property<N, V>
+where
+    N: Name,
+    V: AsRef<str>,
+{
+    /// Check for a non-empty value.
+    #[must_use]
+    pub fn has_value(&self) -> bool {
+        !self.is_empty_value()
+    }
+
+    /// Check for an empty value.
+    ///
+    /// Properties with an empty value are encoded as `name=`.
+    #[must_use]
+    pub fn is_empty_value(&self) -> bool {
+        self.value.as_ref().is_empty()
+    }
+
+    /// Encode a property as a `name=value` string.
+    ///
+    /// Both name and value are percent-encoded in the same
+    /// way as when encoding the properties of a tag.
+    ///
+    /// The property must be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`fmt::Error`] if writing into the buffer fails.
+    pub fn encode_into<W: fmt::Write>(&self, write: &mut W) -> fmt::Result {
+        self.encode_into_separated(write, PropSeparator::default())
+    }
+
+    /// Encode a property for joining it with the given separator.
+    ///
+    /// Occurrences of the separator in the name or value are escaped.
+    pub(crate) fn encode_into_separated<W: fmt::Write>(
+        &self,
+        write: &mut W,
+        separator: PropSeparator,
+    ) -> fmt::Result {
+        debug_assert!(self.is_valid());
+        let ascii_set = separator.ascii_set();
+        let encoded_name = percent_encode(self.name().as_ref().as_bytes(), ascii_set);
+        let encoded_value = percent_encode(self.value().as_ref().as_bytes(), ascii_set);
+        write.write_fmt(format_args!("{encoded_name}={encoded_value}"))
+    }
+
+    /// Encode a property as a `name=value` string.
+    ///
+    /// The property must be valid.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<N, V> Property<N, V>
+where
+    N: Name,
+    V: Value,
+{
+    /// Decode a property from an encoded `name=value` string.
+    ///
+    /// Both `name` and `name=` are decoded as a property with an
+    /// empty value, like when decoding the properties of a tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the input cannot be decoded as a valid property.
+    pub fn decode_str(encoded: &str) -> Result<Self, DecodeError> {
+        crate::decode_prop(encoded.as_bytes(), &DecodeOptions::default())
+    }
+}
+
+impl<'a> BorrowedProperty<'a> {
+    /// Decode a property from an encoded `name=value` string without copying.
+    ///
+    /// See also: [`Property::decode_str()`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if the input cannot be decoded as a valid property.
+    pub fn decode_borrowed(encoded: &'a str) -> Result<Self, DecodeError> {
+        crate::decode_prop_borrowed(encoded.as_bytes(), &DecodeOptions::default())
+    }
+
+    /// Convert into a property that owns its name and value.
+    ///
+    /// Owned components are moved without copying.
+    #[must_use]
+    pub fn into_owned<N, V>(self) -> Property<N, V>
+    where
+        N: Name,
+        V: Value,
+    {
+        let Self { name, value } = self;
+        Property {
+            name: N::from_cow_str(name),
+            value: V::from_cow_str(value),
+        }
+    }
+}
+
+impl<N, V> fmt::Display for Property<N, V>
+where
+    N: Name,
+    V: AsRef<str>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.encode_into(f)
+    }
+}
+
+impl<N, V> Property<N, V>
+where
+    N: AsRef<str>,
+    V: AsRef<str>,
+{
+    /// Compare properties by name and then by value.
+    ///
+    /// This is the canonical order of properties.
+    #[must_use]
+    pub fn cmp_canonical(&self, other: &Self) -> Ordering {
+        self.name
+            .as_ref()
+            .cmp(other.name.as_ref())
+            .then_with(|| self.value.as_ref().cmp(other.value.as_ref()))
     }
 }
 
 /// Property with a `CompactString` representation for names
 pub type CompactProperty<V> = Property<CompactName, V>;
+
+/// A property that borrows from the encoded input
+///
+/// Components without percent-encoded characters are borrowed
+/// from the encoded input without allocating.
+pub type BorrowedProperty<'a> = Property<Cow<'a, str>, Cow<'a, str>>;
@@ -19,6 +19,8 @@ type DecodedTags = super::DecodedTags<Facet, Label, props::CompactName, CompactS
 #[test]
 fn empty_tag_is_invalid() {
     assert!(!Tag::default().is_valid());
+    assert!(!Tag::empty().is_valid());
+    assert_eq!(Tag::default(), Tag::empty());
 }
 
 #[test]
@@ -405,3 +407,2135 @@ fn reorder_and_dedup2() {
         reencoded
     );
 }
+
+#[test]
+fn write_tags_to_io_sink() {
+    let encoded = "Some text\n#Tag1 wishlist@20220526#Someone @20220625?name=val";
+    let decoded = DecodedTags::decode_str(encoded);
+    assert_eq!(3, decoded.tags.len());
+    let mut written = Vec::new();
+    decoded.tags[1].write_to(&mut written).unwrap();
+    assert_eq!(b"wishlist@20220526#Someone".as_slice(), written);
+    let mut written = Vec::new();
+    decoded.write_to(&mut written).unwrap();
+    let mut reencoded = String::new();
+    decoded.encode_into(&mut reencoded).unwrap();
+    assert_eq!(reencoded.as_bytes(), written);
+}
+
+#[test]
+fn write_tags_to_io_sink_should_forward_io_errors() {
+    struct FailingSink;
+
+    impl std::io::Write for FailingSink {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WriteZero.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let tag = Tag::decode_str("#label").unwrap();
+    let err = tag.write_to(&mut FailingSink).unwrap_err();
+    assert_eq!(std::io::ErrorKind::WriteZero, err.kind());
+}
+
+#[test]
+fn should_reject_overencoded_input_only_if_requested() {
+    let options = DecodeOptions {
+        reject_overencoding: true,
+        ..Default::default()
+    };
+    for encoded in [
+        "#My%20Label",
+        "a%20facet@20220625",
+        "facet?name=val%26ue",
+        "!%23$&'()*+,/:;=%3F@[]%25Facet#%25Label",
+        "#%C3%A4",
+        "facet%3F#label",
+        "facet?name=val%3Due",
+    ] {
+        assert!(Tag::decode_str_with_options(encoded, &options).is_ok());
+    }
+    for encoded in [
+        "#My%41Label",
+        "%61facet@20220625",
+        "facet?n%61me=value",
+        "facet?name=v%61lue",
+        "#label%2B",
+        "#%c3%a4",
+        "#%C3%a4",
+        "facet%3f#label",
+        "facet?name=val%3due",
+    ] {
+        assert!(Tag::decode_str(encoded).is_ok());
+        assert!(Tag::decode_str_with_options(encoded, &options).is_err());
+        assert!(Tag::decode_str_with_options(encoded, &DecodeOptions::strict()).is_err());
+    }
+}
+
+#[test]
+fn replace_tags_should_preserve_undecoded_prefix() {
+    let undecoded_prefix = "Some comment\n";
+    let mut decoded = DecodedTags::decode_str(&format!("{undecoded_prefix}#Tag1 #Tag2"));
+    assert_eq!(2, decoded.tags.len());
+    decoded.replace_tags([
+        Tag::decode_str("#Tag3").unwrap(),
+        Tag::decode_str("@20220625").unwrap(),
+        Tag::decode_str("#Tag3").unwrap(),
+    ]);
+    decoded.reorder_and_dedup();
+    assert_eq!(undecoded_prefix, decoded.undecoded_prefix);
+    assert_eq!(
+        format!("{undecoded_prefix}#Tag3 @20220625"),
+        decoded.reencode().unwrap()
+    );
+}
+
+#[test]
+fn cmp_chronological() {
+    let mut decoded = DecodedTags::decode_str(
+        "wishlist@20220625#B @20220624#Label #Z wishlist@20220625#A played@20200101 facet?name=val",
+    );
+    decoded.tags.sort_by(Tag::cmp_chronological);
+    assert_eq!(
+        "#Z facet?name=val played@20200101 @20220624#Label wishlist@20220625#A wishlist@20220625#B",
+        decoded.reencode().unwrap()
+    );
+}
+
+#[test]
+fn encode_checked() {
+    let tag = Tag::decode_str("wishlist@20220625#Label").unwrap();
+    assert_eq!("wishlist@20220625#Label", tag.encode_checked());
+}
+
+#[test]
+#[should_panic(expected = "invalid tag")]
+fn encode_checked_should_panic_on_incomplete_tag() {
+    let tag = Tag {
+        facet: Facet::from_str("non-date-like-facet"),
+        ..Default::default()
+    };
+    drop(tag.encode_checked());
+}
+
+#[test]
+#[should_panic(expected = "invalid facet")]
+fn assert_valid_should_panic_on_invalid_facet() {
+    let tag = Tag {
+        label: Label::from_str("Label"),
+        facet: Facet::from_str("facet @20220625"),
+        ..Default::default()
+    };
+    tag.assert_valid();
+}
+
+#[test]
+fn escaped_bytes() {
+    use encoding::{escaped_bytes, Component};
+
+    let facet = escaped_bytes(Component::Facet);
+    let label = escaped_bytes(Component::Label);
+    let prop_name = escaped_bytes(Component::PropName);
+    let prop_value = escaped_bytes(Component::PropValue);
+    for bytes in [&facet, &label, &prop_name, &prop_value] {
+        assert!(bytes.windows(2).all(|w| w[0] < w[1]));
+        assert!(bytes.contains(&b'%'));
+        assert!(bytes.contains(&b' '));
+        assert!(bytes.contains(&0x7f));
+        assert!(!bytes.contains(&b'A'));
+    }
+    assert!(facet.contains(&b'#'));
+    assert!(facet.contains(&b'?'));
+    assert!(!facet.contains(&b'/'));
+    assert!(!label.contains(&b'#'));
+    assert!(!label.contains(&b'?'));
+    assert!(prop_name.contains(&b'&'));
+    assert!(prop_name.contains(&b'='));
+    assert!(!facet.contains(&b'='));
+    assert_eq!(prop_name, prop_value);
+}
+
+#[test]
+fn canonicalize_all_and_reorder_and_dedup_should_produce_stable_encoding() {
+    let mut lhs = DecodedTags::decode_str(
+        "facet?b=2&a=1#Label facet?c=3#Label @20220625?y=&x=0 facet?a=1&b=2#Label",
+    );
+    let mut rhs = DecodedTags::decode_str("@20220625?x=0&y= facet?c=3#Label facet?a=1&b=2#Label");
+    for decoded in [&mut lhs, &mut rhs] {
+        decoded.canonicalize_all();
+        decoded.reorder_and_dedup();
+    }
+    assert_eq!(3, lhs.tags.len());
+    assert_eq!(lhs, rhs);
+    assert_eq!(
+        "facet?a=1&b=2#Label facet?c=3#Label @20220625?x=0&y=",
+        lhs.reencode().unwrap()
+    );
+}
+
+#[test]
+fn decode_str_repaired() {
+    let (tag, repaired) = Tag::decode_str_repaired("wishlist@20220625#x").unwrap();
+    assert!(!repaired);
+    assert_eq!("wishlist@20220625#x", tag.encode());
+    for encoded in [
+        "/wishlist@20220625#x",
+        "//wishlist@20220625#x",
+        " /wishlist@20220625#x\t",
+        "/ wishlist@20220625#x",
+    ] {
+        assert!(Tag::decode_str(encoded).is_err());
+        let (tag, repaired) = Tag::decode_str_repaired(encoded).unwrap();
+        assert!(repaired);
+        assert_eq!("wishlist@20220625#x", tag.encode());
+    }
+    assert!(Tag::decode_str_repaired("/").is_err());
+}
+
+#[test]
+fn encode_property() {
+    let prop: Property<props::CompactName, CompactString> = Property {
+        name: props::Name::from_str("a&b=c"),
+        value: props::Value::from_str("#1 + 2?"),
+    };
+    assert_eq!("a%26b%3Dc=%231%20+%202?", prop.encode());
+    let tag = Tag {
+        facet: Facet::from_str("facet"),
+        props: vec![prop.clone(), prop],
+        ..Default::default()
+    };
+    assert_eq!(
+        "facet?a%26b%3Dc=%231%20+%202?&a%26b%3Dc=%231%20+%202?",
+        tag.encode()
+    );
+}
+
+#[test]
+fn group_by_date() {
+    let decoded = DecodedTags::decode_str(
+        "#A wishlist@20220625#B @20220624#C @99999999 played@20220625 facet?name=val",
+    );
+    let groups = decoded.group_by_date();
+    let date = |day| Date::from_calendar_date(2022, time::Month::June, day).unwrap();
+    assert_eq!(
+        vec![None, Some(date(24)), Some(date(25))],
+        groups.keys().copied().collect::<Vec<_>>()
+    );
+    let encoded_groups = groups
+        .values()
+        .map(|tags| tags.iter().map(|tag| tag.encode()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            vec!["#A", "@99999999", "facet?name=val"],
+            vec!["@20220624#C"],
+            vec!["wishlist@20220625#B", "played@20220625"],
+        ],
+        encoded_groups
+    );
+}
+
+#[test]
+fn validate() {
+    assert_eq!(
+        Ok(()),
+        Tag::decode_str("facet?name=val").unwrap().validate()
+    );
+    assert_eq!(
+        Err(TagInvalidity::MissingLabelAndFacet),
+        Tag::default().validate()
+    );
+    assert_eq!(
+        Err(TagInvalidity::FacetOnlyWithoutDate),
+        Tag {
+            facet: Facet::from_str("facet"),
+            ..Default::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(TagInvalidity::Facet(FacetInvalidity::InvalidDateLikeSuffix)),
+        Tag {
+            facet: Facet::from_str("facet @20220625"),
+            label: Label::from_str("label"),
+            ..Default::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(TagInvalidity::Label(LabelInvalidity::LeadingSlash)),
+        Tag {
+            label: Label::from_str("/label"),
+            ..Default::default()
+        }
+        .validate()
+    );
+    assert_eq!(
+        Err(TagInvalidity::PropName),
+        Tag {
+            label: Label::from_str("label"),
+            props: vec![Property {
+                name: props::Name::from_str(""),
+                value: props::Value::from_str("value"),
+            }],
+            ..Default::default()
+        }
+        .validate()
+    );
+    let prop = Property {
+        name: props::Name::from_str(" name"),
+        value: props::Value::from_str("value"),
+    };
+    assert!(!prop.is_valid());
+    assert_eq!(
+        Err(TagInvalidity::PropName),
+        Tag {
+            label: Label::from_str("label"),
+            props: vec![prop],
+            ..Default::default()
+        }
+        .validate()
+    );
+}
+
+#[test]
+fn mutations_should_either_preserve_validity_or_fail() {
+    let mut tag = Tag::decode_str("facet?name=val#Label").unwrap();
+    assert!(tag.strip_props().is_ok());
+    assert_eq!("facet#Label", tag.encode());
+    assert_eq!(
+        Err(TagInvalidity::FacetOnlyWithoutDate),
+        tag.set_label(Label::default())
+    );
+    assert_eq!("facet#Label", tag.encode());
+    assert_eq!(
+        Err(TagInvalidity::Label(
+            LabelInvalidity::LeadingOrTrailingWhitespace
+        )),
+        tag.set_label(Label::from_str("Label "))
+    );
+    assert_eq!(
+        Err(TagInvalidity::Facet(FacetInvalidity::LeadingSlash)),
+        tag.set_facet(Facet::from_str("/facet"))
+    );
+    assert_eq!("facet#Label", tag.encode());
+    assert!(tag.set_facet(Facet::from_str("facet@20220625")).is_ok());
+    assert!(tag.set_label(Label::default()).is_ok());
+    assert_eq!("facet@20220625", tag.encode());
+    assert_eq!(
+        Err(TagInvalidity::FacetOnlyWithoutDate),
+        tag.set_facet(Facet::from_str("facet"))
+    );
+    assert_eq!(
+        Err(TagInvalidity::MissingLabelAndFacet),
+        tag.set_facet(Facet::default())
+    );
+    assert_eq!("facet@20220625", tag.encode());
+    assert_eq!(Ok(()), tag.validate());
+
+    let mut tag = Tag::decode_str("facet?name=val").unwrap();
+    assert_eq!(Err(TagInvalidity::FacetOnlyWithoutDate), tag.strip_props());
+    assert_eq!("facet?name=val", tag.encode());
+}
+
+#[test]
+fn decode_str_with_spans() {
+    let encoded = "Prefix #Tag1\t#Tag%202  wishlist@20220526#Someone \t";
+    let (decoded, spans) = DecodedTags::decode_str_with_spans(encoded);
+    assert_eq!(decoded, DecodedTags::decode_str(encoded));
+    assert_eq!(
+        vec!["#Tag1", "#Tag%202", "wishlist@20220526#Someone"],
+        spans
+            .iter()
+            .map(|span| &encoded[span.clone()])
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn encode_into_with_spans_should_preserve_unmodified_contents() {
+    let encoded = " \t#Tag%31\t#Tag%202  wishlist@20220526?b=&a#Someone \t";
+    let (mut decoded, spans) = DecodedTags::decode_str_with_spans(encoded);
+    let mut reencoded = String::new();
+    decoded
+        .encode_into_with_spans(encoded, &spans, &mut reencoded)
+        .unwrap();
+    assert_eq!(encoded, reencoded);
+
+    decoded.tags[1].label = Label::from_str("Tag 3");
+    let mut reencoded = String::new();
+    decoded
+        .encode_into_with_spans(encoded, &spans, &mut reencoded)
+        .unwrap();
+    assert_eq!(
+        " \t#Tag%31\t#Tag%203  wishlist@20220526?b=&a#Someone \t",
+        reencoded
+    );
+
+    decoded.undecoded_prefix = "Prefix".into();
+    decoded.tags.push(Tag::decode_str("@20220625").unwrap());
+    let mut reencoded = String::new();
+    decoded
+        .encode_into_with_spans(encoded, &spans, &mut reencoded)
+        .unwrap();
+    assert_eq!(
+        "Prefix #Tag%31\t#Tag%203  wishlist@20220526?b=&a#Someone @20220625",
+        reencoded
+    );
+}
+
+#[test]
+fn facet_prefix_and_date() {
+    let date = Date::from_calendar_date(2022, time::Month::June, 25).unwrap();
+    let tag = Tag::decode_str("wishlist@20220625#Label").unwrap();
+    assert_eq!(("wishlist", Some(date)), tag.facet_prefix_and_date());
+    let tag = Tag::decode_str("@20220625").unwrap();
+    assert_eq!(("", Some(date)), tag.facet_prefix_and_date());
+    let tag = Tag::decode_str("@99999999").unwrap();
+    assert_eq!(("", None), tag.facet_prefix_and_date());
+    let tag = Tag::decode_str("wishlist#Label").unwrap();
+    assert_eq!(("wishlist", None), tag.facet_prefix_and_date());
+    let tag = Tag::decode_str("#Label").unwrap();
+    assert_eq!(("", None), tag.facet_prefix_and_date());
+}
+
+#[test]
+fn sort_by_policy() {
+    let encoded = "wishlist@20220625#B #Z @20220624#A facet?name=val facet#A";
+    let sorted = |policy| {
+        let mut decoded = DecodedTags::decode_str(encoded);
+        decoded.sort_by_policy(policy);
+        decoded.reencode().unwrap()
+    };
+    assert_eq!(
+        "#Z facet#A facet?name=val wishlist@20220625#B @20220624#A",
+        sorted(SortPolicy::Canonical)
+    );
+    assert_eq!(
+        "#Z facet?name=val facet#A @20220624#A wishlist@20220625#B",
+        sorted(SortPolicy::Chronological)
+    );
+    assert_eq!(
+        "facet?name=val @20220624#A facet#A wishlist@20220625#B #Z",
+        sorted(SortPolicy::Label)
+    );
+    assert_eq!(
+        "#Z @20220624#A facet?name=val facet#A wishlist@20220625#B",
+        sorted(SortPolicy::Facet)
+    );
+}
+
+#[test]
+fn should_reject_multiple_date_like_suffixes_only_if_requested() {
+    let options = DecodeOptions {
+        reject_multiple_date_like_suffixes: true,
+        ..Default::default()
+    };
+    assert!(Tag::decode_str_with_options("text@20230101", &options).is_ok());
+    assert!(Tag::decode_str_with_options("text@2022010@20230101", &options).is_ok());
+    assert!(Tag::decode_str("text@20220101@20230101").is_ok());
+    assert!(Tag::decode_str_with_options("text@20220101@20230101", &options).is_err());
+    assert!(Tag::decode_str_with_options("@20220101@20230101", &options).is_err());
+}
+
+#[test]
+fn url_conversion_roundtrip() {
+    for encoded in [
+        "#My%20Label",
+        "@20220625",
+        "a/facet:with%20colon@20220625?name=val&x=#Label",
+        "!%23$&'()*+,/:;=%3F@[]%25Facet?a%26b=%3D#!#$&'()*+,/:;=?@[]%25Label",
+    ] {
+        let tag = Tag::decode_str(encoded).unwrap();
+        let url = tag.to_url();
+        assert_eq!("dummy", url.scheme());
+        assert_eq!(tag, Tag::try_from(&url).unwrap());
+        assert_eq!(url, Url::from(&tag));
+    }
+}
+
+#[test]
+fn url_conversion_should_reject_foreign_urls() {
+    for url in [
+        "https://example.com/facet#label",
+        "dummy://host/facet#label",
+        "dummy:facet#label",
+        "dummy:///facet",
+    ] {
+        let url: Url = url.parse().unwrap();
+        assert!(Tag::try_from(&url).is_err());
+    }
+    assert!(Tag::decode_str("dummy:facet#label").is_err());
+}
+
+#[test]
+fn lint_decoded_tags() {
+    let decoded = DecodedTags::decode_str(
+        "#Label played@99999999 ?b=1&a=#Label #Label played@20220625?x= #Other",
+    );
+    assert!(decoded.undecoded_prefix.is_empty());
+    assert_eq!(
+        vec![
+            TagLint {
+                index: 1,
+                kind: TagLintKind::InvalidDateSuffix,
+            },
+            TagLint {
+                index: 2,
+                kind: TagLintKind::EmptyPropValue("a"),
+            },
+            TagLint {
+                index: 2,
+                kind: TagLintKind::NonCanonicalPropOrder,
+            },
+            TagLint {
+                index: 3,
+                kind: TagLintKind::DuplicateOf(0),
+            },
+            TagLint {
+                index: 4,
+                kind: TagLintKind::EmptyPropValue("x"),
+            },
+        ],
+        decoded.lint()
+    );
+    assert!(DecodedTags::decode_str("#A #B played@20220625")
+        .lint()
+        .is_empty());
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn binary_roundtrip() {
+    let decoded = DecodedTags::decode_str(
+        "Some text\n#Label played@20220625 facet?name=val&empty=#With%20Space",
+    );
+    assert_eq!(3, decoded.tags.len());
+    let bytes = decoded.to_bytes();
+    assert_eq!(Some(&binary::FORMAT_VERSION), bytes.first());
+    assert_eq!(decoded, DecodedTags::from_bytes(&bytes).unwrap());
+
+    assert!(DecodedTags::from_bytes(&[]).is_err());
+    assert!(DecodedTags::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut unsupported_version = bytes.clone();
+    unsupported_version[0] = binary::FORMAT_VERSION + 1;
+    assert!(DecodedTags::from_bytes(&unsupported_version).is_err());
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert!(DecodedTags::from_bytes(&trailing).is_err());
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn binary_rejects_invalid_components() {
+    fn encode_tag(facet: &str, label: &str, props: &[(&str, &str)]) -> Vec<u8> {
+        fn write_str(bytes: &mut Vec<u8>, s: &str) {
+            bytes.extend_from_slice(&u32::try_from(s.len()).unwrap().to_le_bytes());
+            bytes.extend_from_slice(s.as_bytes());
+        }
+        let mut bytes = vec![binary::FORMAT_VERSION];
+        write_str(&mut bytes, "");
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        write_str(&mut bytes, facet);
+        write_str(&mut bytes, label);
+        bytes.extend_from_slice(&u32::try_from(props.len()).unwrap().to_le_bytes());
+        for (name, value) in props {
+            write_str(&mut bytes, name);
+            write_str(&mut bytes, value);
+        }
+        bytes
+    }
+
+    assert!(DecodedTags::from_bytes(&encode_tag("", "label", &[])).is_ok());
+    assert!(DecodedTags::from_bytes(&encode_tag("", " label", &[])).is_err());
+    assert!(DecodedTags::from_bytes(&encode_tag("/facet", "label", &[])).is_err());
+    assert!(DecodedTags::from_bytes(&encode_tag("x @20220625", "", &[])).is_err());
+    assert!(DecodedTags::from_bytes(&encode_tag("facet", "", &[(" name", "value")])).is_err());
+    assert!(DecodedTags::from_bytes(&encode_tag("facet", "", &[])).is_err());
+}
+
+#[test]
+fn parse_decoded_tags() {
+    let encoded = "Some text #Label played@20220625";
+    assert_eq!(
+        DecodedTags::decode_str(encoded),
+        encoded.parse::<DecodedTags>().unwrap()
+    );
+}
+
+#[test]
+fn get_prop_ignore_ascii_case() {
+    let tag = Tag::decode_str("facet?Rating=5&rating=3&Genre=House").unwrap();
+    assert_eq!(Some("5"), tag.get_prop("Rating").map(AsRef::as_ref));
+    assert_eq!(Some("3"), tag.get_prop("rating").map(AsRef::as_ref));
+    assert!(!tag.has_prop("genre"));
+    assert_eq!(
+        Some("House"),
+        tag.get_prop_ignore_ascii_case("genre").map(AsRef::as_ref)
+    );
+    assert_eq!(
+        Some("5"),
+        tag.get_prop_ignore_ascii_case("RATING").map(AsRef::as_ref)
+    );
+    assert!(tag.has_prop_ignore_ascii_case("GENRE"));
+    assert!(!tag.has_prop_ignore_ascii_case("bpm"));
+}
+
+#[test]
+fn reorder_dedup_and_canonicalize() {
+    let mut decoded =
+        DecodedTags::decode_str("#B facet?b=1&a=2#A #A facet?a=2&b=1#A facet?a=2&b=1#A");
+    assert_eq!(5, decoded.tags.len());
+    decoded.reorder_dedup_and_canonicalize();
+    assert_eq!("#A #B facet?a=2&b=1#A", decoded.reencode().unwrap());
+}
+
+#[test]
+fn identity_key() {
+    let decoded = DecodedTags::decode_str("facet?a=1#Label #Label facet?b=2#Label");
+    let mut index = std::collections::HashMap::<_, Vec<_>>::new();
+    for tag in &decoded.tags {
+        index.entry(tag.identity_key()).or_default().push(tag);
+    }
+    assert_eq!(2, index.len());
+    assert_eq!(2, index[&("facet", "Label")].len());
+    assert_eq!(1, index[&("", "Label")].len());
+}
+
+#[test]
+fn decode_str_with_validator() {
+    let validator = |component: &DecodedComponent<'_>| match component {
+        DecodedComponent::Facet(facet) if !facet.starts_with("app-") => {
+            Err(format!("unknown facet {facet}"))
+        }
+        DecodedComponent::Prop { name, .. } if *name == "secret" => {
+            Err("forbidden property".to_owned())
+        }
+        _ => Ok(()),
+    };
+    assert!(Tag::decode_str_with_validator("#Label", &validator).is_ok());
+    assert!(Tag::decode_str_with_validator("app-facet?name=val", &validator).is_ok());
+    assert!(matches!(
+        Tag::decode_str_with_validator("other?name=val", &validator),
+        Err(DecodeError::Rejected(msg)) if msg == "unknown facet other"
+    ));
+    assert!(matches!(
+        Tag::decode_str_with_validator("app-facet?secret=val", &validator),
+        Err(DecodeError::Rejected(_))
+    ));
+    assert!(matches!(
+        Tag::decode_str_with_validator("app-facet", &validator),
+        Err(DecodeError::InvalidTag)
+    ));
+}
+
+#[test]
+fn extend_decoded_tags() {
+    let other = DecodedTags::decode_str("#B #C");
+    let mut decoded = DecodedTags::decode_str("Text #A");
+    decoded.extend(other.tags.iter());
+    assert_eq!(3, decoded.tags.len());
+    decoded.extend(other.tags);
+    assert_eq!("Text #A #B #C #B #C", decoded.reencode().unwrap());
+}
+
+#[test]
+fn normalize_field_whitespace() {
+    let input = "Some\ttext \r\n#A \u{a0} #B";
+    assert!(matches!(
+        super::normalize_field_whitespace("Text\n#A #B", true),
+        Cow::Borrowed(_)
+    ));
+    assert_eq!(
+        "Some\ttext \n#A \u{a0} #B",
+        super::normalize_field_whitespace(input, false)
+    );
+    assert_eq!(
+        "Some text \n#A \u{a0} #B",
+        super::normalize_field_whitespace(input, true)
+    );
+    assert_eq!(
+        "a b\r c ",
+        super::normalize_field_whitespace("a  b\r \t c\t\t", true)
+    );
+}
+
+#[test]
+fn value_coercions() {
+    let tag = Tag::decode_str("facet?bpm=128&energy=0.78&explicit=1&live=false&note=x").unwrap();
+    let value = |name| tag.get_prop(name).unwrap();
+    assert_eq!(Ok(128u16), value("bpm").parse());
+    assert!(value("note").parse::<u16>().is_err());
+    assert_eq!(Some(0.78), value("energy").as_f64());
+    assert_eq!(Some(128.0), value("bpm").as_f64());
+    assert_eq!(None, value("note").as_f64());
+    assert_eq!(Some(true), value("explicit").as_bool());
+    assert_eq!(Some(false), value("live").as_bool());
+    assert_eq!(None, value("bpm").as_bool());
+}
+
+#[test]
+fn encode_into_ordered() {
+    let tag = Tag::decode_str("facet?note=x&bpm=128&rating=5#Label").unwrap();
+    let priority = ["rating", "note"];
+    let rank = |prop: &Property<_, _>| {
+        priority
+            .iter()
+            .position(|name| *name == AsRef::<str>::as_ref(prop.name()))
+            .unwrap_or(priority.len())
+    };
+    let mut encoded = String::new();
+    tag.encode_into_ordered(&mut encoded, |lhs, rhs| rank(lhs).cmp(&rank(rhs)))
+        .unwrap();
+    assert_eq!("facet?rating=5&note=x&bpm=128#Label", encoded);
+    // The tag is not modified.
+    assert_eq!("facet?note=x&bpm=128&rating=5#Label", tag.encode());
+}
+
+#[test]
+fn decode_str_preserving_source() {
+    let encoded = "Text  #A  facet?b=%31#%42 ";
+    let decoded = DecodedTags::decode_str_preserving_source(encoded);
+    assert_eq!("Text  ", decoded.undecoded_prefix);
+    assert_eq!(
+        vec!["#A", "facet?b=%31#%42"],
+        decoded
+            .tags
+            .iter()
+            .map(|(_, source)| source.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!("facet?b=1#B", decoded.tags[1].0.encode());
+    assert_eq!(
+        DecodedTags::decode_str(encoded),
+        decoded.into_decoded_tags()
+    );
+}
+
+#[test]
+fn encoded_tags_equivalent() {
+    assert!(super::encoded_tags_equivalent(
+        "facet?a=1&b=2#Label",
+        "facet?b=2&a=1#Label"
+    ));
+    assert!(super::encoded_tags_equivalent(
+        "facet?name#%4Cabel",
+        "facet?name=#Label"
+    ));
+    assert!(!super::encoded_tags_equivalent(
+        "facet?a=1#Label",
+        "facet?a=2#Label"
+    ));
+    assert!(!super::encoded_tags_equivalent("#Label", "#label"));
+    assert!(!super::encoded_tags_equivalent(
+        "/invalid#Label",
+        "/invalid#Label"
+    ));
+}
+
+#[test]
+fn into_tags_discards_undecoded_prefix() {
+    let decoded = DecodedTags::decode_str("Some text #A #B");
+    assert_eq!(decoded.tags.as_slice(), decoded.tags());
+    let tags = decoded.into_tags();
+    assert_eq!(2, tags.len());
+    assert_eq!("A", tags[0].label().as_ref());
+}
+
+#[test]
+fn owned_label_and_name_are_moved_without_reallocation() {
+    let heap_allocated = || "a string that does not fit into an inline string".to_owned();
+
+    let label = heap_allocated();
+    let ptr = label.as_ptr();
+    assert_eq!(ptr, Label::from_string(label).as_ptr());
+    let label = heap_allocated();
+    let ptr = label.as_ptr();
+    assert_eq!(
+        ptr,
+        label::StdLabel::from_cow_str(Cow::Owned(label)).as_ptr()
+    );
+
+    let name = heap_allocated();
+    let ptr = name.as_ptr();
+    assert_eq!(ptr, props::CompactName::from_string(name).as_ptr());
+    let name = heap_allocated();
+    let ptr = name.as_ptr();
+    assert_eq!(ptr, props::StdName::from_cow_str(Cow::Owned(name)).as_ptr());
+}
+
+#[test]
+fn reencode_within() {
+    let encoded = "Text #A played@20220625 played@20210101 #B";
+    let decoded = DecodedTags::decode_str(encoded);
+    assert_eq!(4, decoded.tags.len());
+    assert_eq!(
+        Some(encoded.to_owned()),
+        decoded
+            .clone()
+            .reencode_within(encoded.len(), TruncatePolicy::DropTail)
+    );
+    assert_eq!(
+        Some("Text #A played@20220625 played@20210101".to_owned()),
+        decoded
+            .clone()
+            .reencode_within(encoded.len() - 1, TruncatePolicy::DropTail)
+    );
+    assert_eq!(
+        Some("Text #A played@20220625 #B".to_owned()),
+        decoded
+            .clone()
+            .reencode_within(encoded.len() - 1, TruncatePolicy::DropOldestFirst)
+    );
+    assert_eq!(
+        Some("Text #A #B".to_owned()),
+        decoded
+            .clone()
+            .reencode_within(10, TruncatePolicy::DropOldestFirst)
+    );
+    assert_eq!(
+        Some("Text #A".to_owned()),
+        decoded
+            .clone()
+            .reencode_within(9, TruncatePolicy::DropOldestFirst)
+    );
+    assert_eq!(
+        Some("Text ".to_owned()),
+        decoded.clone().reencode_within(6, TruncatePolicy::DropTail)
+    );
+    assert_eq!(None, decoded.reencode_within(4, TruncatePolicy::DropTail));
+}
+
+#[test]
+fn props_from_pairs() {
+    let props = props::props_from_pairs::<props::CompactName, CompactString, _, _>([
+        ("rating", "5"),
+        ("note", ""),
+        ("rating", "4"),
+    ])
+    .unwrap();
+    let tag = Tag {
+        facet: Facet::from_str("facet"),
+        props,
+        ..Default::default()
+    };
+    assert_eq!("facet?rating=5&note=&rating=4", tag.encode());
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("b".to_owned(), "2".to_owned());
+    map.insert("a".to_owned(), "1".to_owned());
+    assert_eq!(
+        2,
+        props::props_from_pairs::<props::CompactName, CompactString, _, _>(map)
+            .unwrap()
+            .len()
+    );
+
+    for invalid_name in ["", " name", "name\t", "/name"] {
+        assert!(
+            props::props_from_pairs::<props::CompactName, CompactString, _, _>([(
+                invalid_name,
+                "value"
+            )])
+            .is_err()
+        );
+    }
+}
+
+#[test]
+fn encode_cow() {
+    for encoded in ["@20220625", "wishlist@20220625"] {
+        let tag = Tag::decode_str(encoded).unwrap();
+        assert!(matches!(tag.encode_cow(), Cow::Borrowed(facet) if facet == encoded));
+    }
+    for encoded in [
+        "My%20Facet@20220625",
+        "@20220625#Label",
+        "@20220625?name=value",
+    ] {
+        let tag = Tag::decode_str(encoded).unwrap();
+        assert!(matches!(tag.encode_cow(), Cow::Owned(owned) if owned == encoded));
+    }
+}
+
+#[test]
+fn semicolon_prop_separator_roundtrip() {
+    let tag = Tag::decode_str("facet?a=1%3B2&b%26c=#Label").unwrap();
+    let encode_options = EncodeOptions {
+        prop_separator: PropSeparator::Semicolon,
+        ..Default::default()
+    };
+    let encoded = tag.encode_with_options(&encode_options);
+    assert_eq!("facet?a=1%3B2;b%26c=#Label", encoded);
+    let decode_options = DecodeOptions {
+        prop_separator: PropSeparator::Semicolon,
+        reject_overencoding: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        tag,
+        Tag::decode_str_with_options(&encoded, &decode_options).unwrap()
+    );
+    // The default separator is unaffected.
+    assert_eq!("facet?a=1;2&b%26c=#Label", tag.encode());
+    assert_eq!(
+        tag.encode(),
+        tag.encode_with_options(&EncodeOptions::default())
+    );
+}
+
+#[test]
+fn decode_tags_separated_by_multi_byte_whitespace() {
+    for (encoded, undecoded_prefix, labels) in [
+        ("#A\u{a0}#B", "", &["A", "B"][..]),
+        ("#Ä\u{3000}#Ö", "", &["Ä", "Ö"]),
+        (
+            "Text\u{2003}#Ä\u{a0}\u{a0}#Ö\u{3000}",
+            "Text\u{2003}",
+            &["Ä", "Ö"],
+        ),
+        ("Ä\u{a0}Text #Ö", "Ä\u{a0}Text ", &["Ö"]),
+        ("#Ä\u{a0}Text\u{a0}#Ö", "#Ä\u{a0}Text\u{a0}", &["Ö"]),
+        ("日本\u{3000}#日本", "日本\u{3000}", &["日本"]),
+    ] {
+        let decoded = DecodedTags::decode_str(encoded);
+        assert_eq!(undecoded_prefix, decoded.undecoded_prefix, "{encoded}");
+        assert_eq!(
+            labels,
+            decoded
+                .tags
+                .iter()
+                .map(|tag| tag.label().as_ref())
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn looks_like_tag() {
+    for token in [
+        "#Label",
+        "@20220625",
+        "played@20220625",
+        "facet?name=value",
+        "facet#Label",
+        // False positives
+        "#",
+        "?",
+        "https://example.com/#anchor",
+    ] {
+        assert!(super::looks_like_tag(token), "{token}");
+    }
+    for token in [
+        "",
+        "Text",
+        "/facet#Label",
+        " #Label",
+        "#Label\n",
+        "played @20220625",
+        "@123456789",
+    ] {
+        assert!(!super::looks_like_tag(token), "{token}");
+        assert!(Tag::decode_str(token).is_err(), "{token}");
+    }
+}
+
+#[test]
+fn decode_single_property() {
+    type Property = super::Property<props::CompactName, CompactString>;
+    let prop = Property::decode_str("my%20name=a%3Db%26c").unwrap();
+    assert_eq!("my name", prop.name().as_ref());
+    assert_eq!("a=b&c", prop.value().as_str());
+    assert_eq!("my%20name=a%3Db%26c", prop.encode());
+    assert_eq!(
+        Property::decode_str("name").unwrap(),
+        Property::decode_str("name=").unwrap()
+    );
+    for invalid in ["", "=value", "%20name=value", "a=b=c", "%FF=value"] {
+        assert!(Property::decode_str(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn decode_should_reject_empty_prop_names() {
+    assert!(Tag::decode_str("?=val#Label").is_err());
+    assert!(Tag::decode_str("facet?&name=val").is_err());
+    assert!(Tag::decode_str("facet?name=val&").is_err());
+}
+
+#[test]
+fn reorder_and_dedup_with_labels_last() {
+    let encoded = "facet?a=1 facet?a=1#B facet?a=1#A facet?a=1";
+    let mut decoded = DecodedTags::decode_str(encoded);
+    decoded.reorder_and_dedup_with_options(&DedupOptions::default());
+    assert_eq!(
+        "facet?a=1#A facet?a=1#B facet?a=1",
+        decoded.reencode().unwrap()
+    );
+    let mut decoded = DecodedTags::decode_str(encoded);
+    decoded.reorder_and_dedup_with_options(&DedupOptions {
+        labels_first: false,
+    });
+    assert_eq!(
+        "facet?a=1 facet?a=1#A facet?a=1#B",
+        decoded.reencode().unwrap()
+    );
+}
+
+#[test]
+fn encode_into_with_spans_should_match_tokens_after_structural_edits() {
+    let encoded = "Text  #a\t#%62  #%63";
+    let (decoded, spans) = DecodedTags::decode_str_with_spans(encoded);
+    let reencode = |decoded: &DecodedTags| {
+        let mut reencoded = String::new();
+        decoded
+            .encode_into_with_spans(encoded, &spans, &mut reencoded)
+            .unwrap();
+        reencoded
+    };
+
+    // Remove the first tag
+    let mut edited = decoded.clone();
+    edited.tags.remove(0);
+    assert_eq!("Text  #%62  #%63", reencode(&edited));
+
+    // Remove a tag in the middle
+    let mut edited = decoded.clone();
+    edited.tags.remove(1);
+    assert_eq!("Text  #a #%63", reencode(&edited));
+
+    // Remove the last tag
+    let mut edited = decoded.clone();
+    edited.tags.pop();
+    assert_eq!("Text  #a\t#%62", reencode(&edited));
+
+    // Insert a tag at the front
+    let mut edited = decoded.clone();
+    edited.tags.insert(0, Tag::decode_str("#new").unwrap());
+    assert_eq!("Text  #new #a\t#%62  #%63", reencode(&edited));
+
+    // Insert a tag in the middle
+    let mut edited = decoded.clone();
+    edited.tags.insert(2, Tag::decode_str("#new").unwrap());
+    assert_eq!("Text  #a\t#%62 #new #%63", reencode(&edited));
+
+    // Replace a tag in the middle
+    let mut edited = decoded.clone();
+    edited.tags[1] = Tag::decode_str("#new").unwrap();
+    assert_eq!("Text  #a\t#new  #%63", reencode(&edited));
+
+    // Swap tags, only the first tag is matched with its original token
+    let mut edited = decoded;
+    edited.tags.swap(0, 2);
+    assert_eq!("Text  #%63 #b #a", reencode(&edited));
+}
+
+#[test]
+fn encode_into_based_on_original() {
+    let original = "Some text\t#%41  facet?b=1&a=2#B\t";
+    let mut decoded = DecodedTags::decode_str(original);
+    let mut encoded = String::new();
+    decoded
+        .encode_into_based_on(original, &mut encoded)
+        .unwrap();
+    assert_eq!(original, encoded);
+
+    decoded.tags[1].canonicalize();
+    let mut encoded = String::new();
+    decoded
+        .encode_into_based_on(original, &mut encoded)
+        .unwrap();
+    assert_eq!("Some text\t#%41  facet?a=2&b=1#B\t", encoded);
+
+    let original = "Text  #a\t#%62  #%63";
+    let mut decoded = DecodedTags::decode_str(original);
+    decoded.tags.remove(0);
+    let mut encoded = String::new();
+    decoded
+        .encode_into_based_on(original, &mut encoded)
+        .unwrap();
+    assert_eq!("Text  #%62  #%63", encoded);
+}
+
+#[test]
+fn edit_components_with_guards() {
+    let mut tag = Tag::decode_str("facet?a=1#Label").unwrap();
+    {
+        let mut props = tag.props_mut();
+        props.push(Property {
+            name: props::CompactName::from_str("b"),
+            value: "2".into(),
+        });
+        props.retain(|prop| prop.name().as_ref() != "a");
+    }
+    *tag.facet_mut() = Facet::from_str("other");
+    assert_eq!("other?b=2#Label", tag.encode());
+
+    let mut tag = Tag::decode_str("facet?a=1").unwrap();
+    let mut props = tag.props_mut();
+    props.clear();
+    assert_eq!(Err(TagInvalidity::FacetOnlyWithoutDate), props.commit());
+    let mut facet = tag.facet_mut();
+    *facet = Facet::from_str(" invalid");
+    assert!(matches!(facet.commit(), Err(TagInvalidity::Facet(_))));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "assertion `left == right` failed")]
+fn dropping_guard_should_panic_on_invalid_tag() {
+    let mut tag = Tag::decode_str("facet?a=1").unwrap();
+    tag.props_mut().clear();
+}
+
+#[test]
+fn leading_bom_is_preserved_in_undecoded_prefix() {
+    for (encoded, undecoded_prefix, reencoded) in [
+        ("\u{feff}#A #B", "\u{feff}", "\u{feff}#A #B"),
+        (
+            "\u{feff}  #A\t#B\n",
+            "\u{feff}  #A\t#B\n",
+            "\u{feff}  #A\t#B\n",
+        ),
+        ("\u{feff}  #A\t#B", "\u{feff}", "\u{feff}#A #B"),
+        ("\u{feff}Text #A", "\u{feff}Text ", "\u{feff}Text #A"),
+        ("\u{feff}", "\u{feff}", "\u{feff}"),
+    ] {
+        let decoded = DecodedTags::decode_str(encoded);
+        assert_eq!(undecoded_prefix, decoded.undecoded_prefix, "{encoded:?}");
+        assert!(decoded.tags.iter().all(|tag| !tag.has_facet()));
+        let (decoded_with_spans, spans) = DecodedTags::decode_str_with_spans(encoded);
+        let mut minimal = String::new();
+        decoded_with_spans
+            .encode_into_with_spans(encoded, &spans, &mut minimal)
+            .unwrap();
+        assert_eq!(encoded, minimal);
+        assert_eq!(reencoded, decoded.reencode().unwrap());
+    }
+}
+
+#[test]
+fn label_substring_matching() {
+    let label = Label::from_str("Floor Filler");
+    assert!(label.contains_str("r F"));
+    assert!(label.starts_with_str("Floor"));
+    assert!(label.ends_with_str("Filler"));
+    assert!(!label.contains_str("filler"));
+    // The pattern-generic string methods are not shadowed
+    assert!(label.contains(' '));
+    assert!(label.ends_with(char::is_alphabetic));
+}
+
+#[test]
+fn search_decoded_tags() {
+    let decoded = DecodedTags::decode_str(
+        "#Floor%20Filler wishlist@20220625 audio?energy=0.78#Dance spotify?genre=House",
+    );
+    let search = |query| decoded.search(query).map(Tag::encode).collect::<Vec<_>>();
+    assert_eq!(vec!["#Floor%20Filler"], search("floor"));
+    assert_eq!(vec!["wishlist@20220625"], search("WISH"));
+    assert_eq!(vec!["audio?energy=0.78#Dance"], search("ENERGY"));
+    assert_eq!(vec!["spotify?genre=House"], search("house"));
+    assert_eq!(
+        vec!["#Floor%20Filler", "audio?energy=0.78#Dance"],
+        search("ER")
+    );
+    assert_eq!(4, search("").len());
+    assert!(search("techno").is_empty());
+
+    // Non-ASCII characters must match exactly
+    let decoded = DecodedTags::decode_str("#L%C3%A4rm #L%C3%84RM");
+    let search = |query| decoded.search(query).map(Tag::encode).collect::<Vec<_>>();
+    assert_eq!(vec!["#L%C3%A4rm"], search("lä"));
+    assert_eq!(vec!["#L%C3%84RM"], search("LÄ"));
+    assert!(search("Lärmen").is_empty());
+}
+
+#[test]
+fn validate_with_policy() {
+    let label_only = Tag::decode_str("#Label").unwrap();
+    let date_only = Tag::decode_str("@20220625").unwrap();
+    let empty_value = Tag::decode_str("facet?name=#Label").unwrap();
+    let default_policy = TagPolicy::default();
+    for tag in [&label_only, &date_only, &empty_value] {
+        assert_eq!(Ok(()), tag.validate_with(&default_policy));
+    }
+    let policy = TagPolicy {
+        require_facet: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Err(PolicyViolation::MissingFacet),
+        label_only.validate_with(&policy)
+    );
+    assert_eq!(Ok(()), date_only.validate_with(&policy));
+    let policy = TagPolicy {
+        require_label: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Err(PolicyViolation::MissingLabel),
+        date_only.validate_with(&policy)
+    );
+    let policy = TagPolicy {
+        forbid_empty_prop_values: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Err(PolicyViolation::EmptyPropValue),
+        empty_value.validate_with(&policy)
+    );
+    assert_eq!(
+        Err(PolicyViolation::Invalid(
+            TagInvalidity::MissingLabelAndFacet
+        )),
+        Tag::default().validate_with(&policy)
+    );
+}
+
+#[test]
+fn build_components_from_format_args() {
+    let (prefix, year) = ("played", 2022);
+    let tag = Tag {
+        facet: Facet::from_format_args(format_args!("{prefix}@{year}0625")),
+        label: Label::from_format_args(format_args!("Set {}", 1)),
+        props: vec![Property {
+            name: props::CompactName::from_format_args(format_args!("bpm{}", "")),
+            value: Value::from_format_args(format_args!("{:.1}", 127.95)),
+        }],
+    };
+    assert_eq!("played@20220625?bpm=128.0#Set%201", tag.encode());
+}
+
+#[test]
+fn canonical_encode_and_strict_decode_are_idempotent() {
+    for encoded in [
+        "facet?b=2&a=&a=1#Label",
+        "facet?b&a=%41#%4Cabel",
+        "x%20y@20220625?z=%26&y=%3D",
+    ] {
+        let tag = Tag::decode_str(encoded).unwrap();
+        let canonical = tag.encode_with_options(&EncodeOptions::canonical());
+        let decoded = Tag::decode_str_with_options(&canonical, &DecodeOptions::strict()).unwrap();
+        assert_eq!(
+            canonical,
+            decoded.encode_with_options(&EncodeOptions::canonical())
+        );
+        let mut canonicalized = tag.clone();
+        canonicalized.canonicalize();
+        assert_eq!(canonicalized.encode(), canonical);
+    }
+    assert_eq!(
+        "facet?a=&a=1&b=2#Label",
+        Tag::decode_str("facet?b=2&a=&a=1#Label")
+            .unwrap()
+            .encode_with_options(&EncodeOptions::canonical())
+    );
+}
+
+#[test]
+fn enumerate_with_date() {
+    let decoded = DecodedTags::decode_str("#Label played@20220625 wishlist@99999999 facet?a=1");
+    let date = Date::from_calendar_date(2022, time::Month::June, 25).unwrap();
+    let expected = [
+        ("", None, "#Label"),
+        ("played", Some(date), "played@20220625"),
+        ("wishlist", None, "wishlist@99999999"),
+        ("facet", None, "facet?a=1"),
+    ];
+    assert_eq!(expected.len(), decoded.enumerate_with_date().count());
+    for ((prefix, date, tag), (expected_prefix, expected_date, expected_encoded)) in
+        decoded.enumerate_with_date().zip(expected)
+    {
+        assert_eq!(expected_prefix, prefix);
+        assert_eq!(expected_date, date);
+        assert_eq!(expected_encoded, tag.encode());
+    }
+}
+
+#[test]
+fn decode_str_lossy() {
+    let encoded = "caf%E9?name=%E9t%E9#Caf%E9";
+    assert!(Tag::decode_str(encoded).is_err());
+    let tag = Tag::decode_str_lossy(encoded).unwrap();
+    assert_eq!("caf\u{fffd}", tag.facet().as_ref());
+    assert_eq!("Caf\u{fffd}", tag.label().as_ref());
+    assert_eq!(
+        Some("\u{fffd}t\u{fffd}"),
+        tag.get_prop("name").map(AsRef::as_ref)
+    );
+    // Valid input is decoded as usual
+    assert_eq!(
+        Tag::decode_str("caf%C3%A9#Label").unwrap(),
+        Tag::decode_str_lossy("caf%C3%A9#Label").unwrap()
+    );
+}
+
+#[test]
+fn is_subsumed_by() {
+    let subsumed = |lhs, rhs| {
+        Tag::decode_str(lhs)
+            .unwrap()
+            .is_subsumed_by(&Tag::decode_str(rhs).unwrap())
+    };
+    assert!(subsumed("#Someone", "wishlist@20220625#Someone"));
+    assert!(subsumed("wishlist#Someone", "wishlist@20220625#Someone"));
+    assert!(subsumed("facet?a=1#Label", "facet?b=2&a=1#Label"));
+    assert!(subsumed("#Label", "#Label"));
+    assert!(subsumed(
+        "wishlist@20220625#Someone",
+        "wishlist@20220625#Someone"
+    ));
+    assert!(!subsumed("wishlist@20220625#Someone", "#Someone"));
+    assert!(!subsumed(
+        "wishlist@20220625#Someone",
+        "wishlist@20230101#Someone"
+    ));
+    assert!(!subsumed("#Someone", "wishlist@20220625#Someone%20Else"));
+    assert!(!subsumed("#Someone", "wishlist@20220625"));
+    assert!(!subsumed("other#Someone", "wishlist@20220625#Someone"));
+    assert!(!subsumed("facet?a=1#Label", "facet?a=2#Label"));
+}
+
+#[test]
+fn try_map_tags() {
+    let mut decoded = DecodedTags::decode_str("wishlist#A wishlist#B other#C");
+    decoded
+        .try_map_tags(|tag| {
+            if tag.facet().as_ref() == "wishlist" {
+                tag.set_facet(Facet::from_str("favorites"))?;
+            }
+            Ok::<_, TagInvalidity>(())
+        })
+        .unwrap();
+    assert_eq!(
+        "favorites#A favorites#B other#C",
+        decoded.reencode().unwrap()
+    );
+
+    let mut decoded = DecodedTags::decode_str("facet#A facet#B facet#C");
+    assert_eq!(
+        Err(TagInvalidity::FacetOnlyWithoutDate),
+        decoded.try_map_tags(|tag| {
+            if tag.label().as_ref() == "B" {
+                tag.set_label(Label::default())?;
+            } else {
+                tag.set_label(Label::from_str("X"))?;
+            }
+            Ok(())
+        })
+    );
+    assert_eq!("facet#X facet#B facet#C", decoded.reencode().unwrap());
+}
+
+#[test]
+fn props_btree_map_conversion() {
+    let tag = Tag::decode_str("facet?b=2&a=1&b=3").unwrap();
+    let map = props::into_btree_map(tag.props.clone());
+    assert_eq!(2, map.len());
+    assert_eq!("1", map[&props::CompactName::from_str("a")]);
+    // Last wins
+    assert_eq!("3", map[&props::CompactName::from_str("b")]);
+    let props = props::props_from_btree_map(map);
+    assert_eq!("a=1&b=3", itertools::join(&props, "&"));
+    assert!(props.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn display_with_width_and_alignment() {
+    let tag = Tag::decode_str("facet#My%20Label").unwrap();
+    assert_eq!("facet#My%20Label", format!("{tag}"));
+    assert_eq!("facet#My%20Label", format!("{tag:5}"));
+    assert_eq!("facet#My%20Label    ", format!("{tag:20}"));
+    assert_eq!("    facet#My%20Label", format!("{tag:>20}"));
+    assert_eq!("**facet#My%20Label**", format!("{tag:*^20}"));
+    assert_eq!("**facet#My%20Label***", format!("{tag:*^21}"));
+    // The precision never truncates the encoded tag
+    assert_eq!("facet#My%20Label", format!("{tag:.3}"));
+    assert_eq!("    facet#My%20Label", format!("{tag:>20.3}"));
+    assert_eq!("facet#My%20Label", format!("{tag:>10.3}"));
+}
+
+#[test]
+fn new_decoded_tags_should_match_decoded_value() {
+    let tag = Tag {
+        label: Label::from_str("label"),
+        ..Default::default()
+    };
+    let decoded = DecodedTags::new("Some text ", vec![tag]);
+    assert_eq!(DecodedTags::decode_str("Some text #label"), decoded);
+    assert_eq!("Some text #label", decoded.reencode().unwrap());
+}
+
+#[test]
+fn is_already_canonical() {
+    let mut decoded_tags = DecodedTags::decode_str("#b #a @20220625#c #a");
+    assert!(!decoded_tags.is_already_canonical());
+    decoded_tags.reorder_and_dedup();
+    assert!(decoded_tags.is_already_canonical());
+    let reencoded = decoded_tags.reencode().unwrap();
+    assert!(DecodedTags::decode_str(&reencoded).is_already_canonical());
+    assert!(DecodedTags::decode_str("").is_already_canonical());
+    assert!(!DecodedTags::decode_str("#a #a").is_already_canonical());
+}
+
+#[test]
+fn encode_and_decode_components_into_reused_buffers() {
+    use encoding::{decode_component_into, encode_component_into, encode_facet_into, Component};
+
+    let mut encoded = String::new();
+    let mut decoded = String::new();
+    for facet in ["a facet", "with/slash", "#?%"] {
+        encoded.clear();
+        decoded.clear();
+        encode_facet_into(facet, &mut encoded);
+        decode_component_into(&encoded, &mut decoded).unwrap();
+        assert_eq!(facet, decoded);
+    }
+    encode_component_into(Component::Label, "x y", &mut encoded);
+    assert_eq!("%23%3F%25x%20y", encoded);
+
+    let mut buf = "prefix".to_owned();
+    assert!(decode_component_into("%FF", &mut buf).is_err());
+    assert_eq!("prefix", buf);
+    decode_component_into("%20suffix", &mut buf).unwrap();
+    assert_eq!("prefix suffix", buf);
+}
+
+#[test]
+fn build_tag_with_consuming_setters() {
+    let tag = Tag::empty()
+        .with_facet(Facet::from_str("facet"))
+        .unwrap()
+        .with_label(Label::from_str("label"))
+        .unwrap()
+        .with_prop(props::CompactName::from_str("name"), "value".into());
+    assert!(tag.is_valid());
+    assert_eq!("facet?name=value#label", tag.encode());
+
+    assert!(Tag::empty().with_label(Label::from_str(" label")).is_err());
+    assert!(Tag::empty().with_facet(Facet::from_str(" facet")).is_err());
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalize_nfc_should_collapse_canonically_equivalent_labels() {
+    let mut decoded = DecodedTags::decode_str("#caf\u{e9} #cafe\u{301} genre/caf\u{e9}@20220625");
+    assert_eq!(3, decoded.tags.len());
+    decoded.reorder_and_dedup();
+    assert_eq!(3, decoded.tags.len());
+    decoded.normalize_nfc();
+    decoded.reorder_and_dedup();
+    assert_eq!(2, decoded.tags.len());
+    assert!(decoded
+        .tags
+        .iter()
+        .all(|tag| !tag.label().as_ref().contains('\u{301}')));
+    assert!(matches!(
+        crate::normalization::nfc("caf\u{e9}"),
+        Cow::Borrowed("caf\u{e9}")
+    ));
+    assert_eq!("caf\u{e9}", crate::normalization::nfc("cafe\u{301}"));
+}
+
+#[test]
+fn enforce_unique_prop_names() {
+    let tag = Tag::decode_str("facet?a=1&b=2&a=3&c=4&b=2").unwrap();
+    assert!(!tag.prop_names_are_unique());
+
+    let mut keep_first = tag.clone();
+    keep_first
+        .enforce_unique_prop_names(DuplicatePolicy::KeepFirst)
+        .unwrap();
+    assert!(keep_first.prop_names_are_unique());
+    assert_eq!("facet?a=1&b=2&c=4", keep_first.encode());
+
+    let mut keep_last = tag.clone();
+    keep_last
+        .enforce_unique_prop_names(DuplicatePolicy::KeepLast)
+        .unwrap();
+    assert_eq!("facet?a=3&c=4&b=2", keep_last.encode());
+
+    let mut reject = tag.clone();
+    let err = reject
+        .enforce_unique_prop_names(DuplicatePolicy::Reject)
+        .unwrap_err();
+    assert_eq!("a", err.name);
+    assert_eq!(tag, reject);
+
+    let mut unique = Tag::decode_str("facet?a=1&b=2").unwrap();
+    assert!(unique.prop_names_are_unique());
+    unique
+        .enforce_unique_prop_names(DuplicatePolicy::Reject)
+        .unwrap();
+}
+
+#[test]
+fn decode_tokens_should_decode_each_token_strictly() {
+    let decoded = Tag::decode_tokens("#a;@20220625; #b".split(';')).collect::<Vec<_>>();
+    assert_eq!(3, decoded.len());
+    assert_eq!("#a", decoded[0].as_ref().unwrap().encode());
+    assert_eq!("@20220625", decoded[1].as_ref().unwrap().encode());
+    assert!(decoded[2].is_err());
+}
+
+#[test]
+fn reorder_and_dedup_should_match_always_sorting() {
+    for encoded in [
+        "",
+        "#a",
+        "#a #a #b",
+        "#b #a #a",
+        "@20220625#c #a @20220624 x?b=1 x?b=1 #a",
+        "x?b=1&a=2 x?a=2&b=1 #a",
+    ] {
+        let mut always_sorted = DecodedTags::decode_str(encoded);
+        always_sorted.sort_by_policy(SortPolicy::Canonical);
+        always_sorted.tags.dedup();
+        let mut decoded = DecodedTags::decode_str(encoded);
+        decoded.reorder_and_dedup();
+        assert_eq!(always_sorted, decoded);
+        // Already sorted input
+        decoded.reorder_and_dedup();
+        assert_eq!(always_sorted, decoded);
+    }
+}
+
+#[test]
+fn validate_with_policy_allowing_facet_only_without_date() {
+    let tag = Tag {
+        facet: Facet::from_str("genre/techno"),
+        ..Default::default()
+    };
+    assert_eq!(Err(TagInvalidity::FacetOnlyWithoutDate), tag.validate());
+    assert_eq!(
+        Err(PolicyViolation::Invalid(
+            TagInvalidity::FacetOnlyWithoutDate
+        )),
+        tag.validate_with(&TagPolicy::default())
+    );
+    let policy = TagPolicy {
+        allow_facet_only_without_date: true,
+        ..Default::default()
+    };
+    assert_eq!(Ok(()), tag.validate_with(&policy));
+    assert_eq!(
+        Err(PolicyViolation::Invalid(
+            TagInvalidity::MissingLabelAndFacet
+        )),
+        Tag::default().validate_with(&policy)
+    );
+
+    // Round trip
+    let encoded = tag.encode();
+    assert_eq!("genre/techno", encoded);
+    assert!(Tag::decode_str(&encoded).is_err());
+    assert!(Tag::decode_str_with_policy(&encoded, &TagPolicy::default()).is_err());
+    assert_eq!(tag, Tag::decode_str_with_policy(&encoded, &policy).unwrap());
+    for encoded in ["/genre/techno", " genre/techno", "genre/techno ", ""] {
+        assert!(Tag::decode_str_with_policy(encoded, &policy).is_err());
+    }
+    // Any word is decoded as a tag, i.e. text must be separated by a newline
+    let encoded_field = "Some text\ngenre/techno #label";
+    let decoded = DecodedTags::decode_str_with_policy(encoded_field, &policy);
+    assert_eq!("Some text\n", decoded.undecoded_prefix);
+    assert_eq!(
+        vec![tag.clone(), Tag::decode_str("#label").unwrap()],
+        decoded.tags
+    );
+    assert_eq!(encoded_field, decoded.reencode().unwrap());
+    let decoded = DecodedTags::decode_str(encoded_field);
+    assert_eq!("Some text\ngenre/techno ", decoded.undecoded_prefix);
+
+    // Other policy violations are rejected
+    let policy = TagPolicy {
+        require_label: true,
+        ..policy
+    };
+    assert!(matches!(
+        Tag::decode_str_with_policy(&encoded, &policy),
+        Err(DecodeError::Rejected(_))
+    ));
+}
+
+#[test]
+fn indexed_access_to_decoded_tags() {
+    let mut decoded = DecodedTags::decode_str("#a #b #c");
+    assert_eq!("#b", decoded.get(1).unwrap().encode());
+    assert!(decoded.get(3).is_none());
+    decoded
+        .get_mut(1)
+        .unwrap()
+        .set_label(Label::from_str("x"))
+        .unwrap();
+    assert!(decoded.get_mut(3).is_none());
+    assert!(decoded.remove(3).is_none());
+    assert_eq!("#a", decoded.remove(0).unwrap().encode());
+    assert_eq!("#x #c", decoded.reencode().unwrap());
+}
+
+#[test]
+fn decoded_tags_from_single_tag() {
+    let tag = Tag::decode_str("facet#label").unwrap();
+    let decoded = DecodedTags::from(tag.clone());
+    assert!(decoded.undecoded_prefix.is_empty());
+    assert_eq!(vec![tag], decoded.tags);
+    assert_eq!("facet#label", decoded.reencode().unwrap());
+}
+
+#[test]
+fn decode_multiple_lines_without_stopping_at_newline() {
+    let encoded = "Some text\n#a\n#b #c\n";
+    let decoded = DecodedTags::decode_str(encoded);
+    assert_eq!(encoded, decoded.undecoded_prefix);
+    assert!(decoded.tags.is_empty());
+    assert_eq!(
+        decoded,
+        DecodedTags::decode_str_with_options(encoded, &DecodeOptions::default())
+    );
+
+    let options = DecodeOptions {
+        stop_at_newline: false,
+        ..Default::default()
+    };
+    let decoded = DecodedTags::decode_str_with_options(encoded, &options);
+    assert_eq!("Some text\n", decoded.undecoded_prefix);
+    assert_eq!(3, decoded.tags.len());
+    assert_eq!("Some text\n#a #b #c", decoded.reencode().unwrap());
+}
+
+#[test]
+fn str_eq_across_backends() {
+    type StdTag = super::Tag<facet::StdFacet, label::StdLabel, props::StdName, String>;
+    let compact = Tag::decode_str("facet?a=1&b=2#label").unwrap();
+    let std = StdTag::decode_str("facet?a=1&b=2#label").unwrap();
+    assert!(compact.str_eq(&std));
+    assert!(std.str_eq(&compact));
+    assert!(compact.str_eq_ignoring_prop_order(&std));
+
+    let reordered = StdTag::decode_str("facet?b=2&a=1#label").unwrap();
+    assert!(!compact.str_eq(&reordered));
+    assert!(compact.str_eq_ignoring_prop_order(&reordered));
+
+    let different = StdTag::decode_str("facet?a=1&b=3#label").unwrap();
+    assert!(!compact.str_eq(&different));
+    assert!(!compact.str_eq_ignoring_prop_order(&different));
+}
+
+#[test]
+fn coerce_label() {
+    for (input, expected) in [
+        ("", ""),
+        (" ", ""),
+        ("//", ""),
+        (" / label ", "label"),
+        ("a/b", "a/b"),
+    ] {
+        let coerced = label::coerce(input);
+        assert_eq!(expected, coerced);
+        assert!(label::is_valid(&coerced));
+    }
+}
+
+#[test]
+fn find_index_of_tags() {
+    let decoded = DecodedTags::decode_str("#a facet#b facet?x=1#b @20220625");
+    assert_eq!(Some(1), decoded.position(Tag::has_facet));
+    assert_eq!(Some(2), decoded.position(|tag| tag.has_prop("x")));
+    assert_eq!(None, decoded.position(|tag| tag.has_prop("y")));
+    assert_eq!(Some(0), decoded.index_of_identity("", "a"));
+    assert_eq!(Some(1), decoded.index_of_identity("facet", "b"));
+    assert_eq!(Some(3), decoded.index_of_identity("@20220625", ""));
+    assert_eq!(None, decoded.index_of_identity("facet", "a"));
+}
+
+#[test]
+fn decode_borrowed_property() {
+    let prop = BorrowedProperty::decode_borrowed("name=value").unwrap();
+    assert!(matches!(prop.name, Cow::Borrowed("name")));
+    assert!(matches!(prop.value, Cow::Borrowed("value")));
+
+    let prop = BorrowedProperty::decode_borrowed("name=a%20value").unwrap();
+    assert!(matches!(prop.name, Cow::Borrowed("name")));
+    assert!(matches!(prop.value, Cow::Owned(_)));
+    assert_eq!("a value", prop.value);
+
+    let owned: props::CompactProperty<CompactString> = prop.into_owned();
+    assert_eq!(
+        props::CompactProperty::<CompactString>::decode_str("name=a%20value").unwrap(),
+        owned
+    );
+    assert!(BorrowedProperty::decode_borrowed("=value").is_err());
+}
+
+#[test]
+fn prop_value_with_fallback() {
+    let tag = Tag::decode_str("facet?a=1&b=").unwrap();
+    let fallback = CompactString::from("fallback");
+    assert_eq!("1", tag.prop_value_or("a", &fallback));
+    assert_eq!("", tag.prop_value_or("b", &fallback));
+    assert_eq!("fallback", tag.prop_value_or("c", &fallback));
+    assert_eq!("1", tag.prop_value_or_default("a"));
+    assert_eq!("", tag.prop_value_or_default("c"));
+}
+
+#[test]
+fn distinct_facet_prefixes() {
+    let decoded = DecodedTags::decode_str(
+        "#label played@20220625 genre#techno played@20220624 @20220101 genre?a=1#house",
+    );
+    assert_eq!(vec!["genre", "played"], decoded.distinct_facet_prefixes());
+    assert!(DecodedTags::decode_str("#label")
+        .distinct_facet_prefixes()
+        .is_empty());
+}
+
+#[test]
+fn decode_str_canonical() {
+    for encoded in ["#label", "facet?a=1&b=2#label", "@20220625?a="] {
+        assert_eq!(
+            Tag::decode_str(encoded).unwrap(),
+            Tag::decode_str_canonical(encoded).unwrap()
+        );
+    }
+    for (encoded, expected_canonical) in [
+        ("#%41", "#A"),
+        ("facet?b=2&a=1", "facet?a=1&b=2"),
+        ("@20220625?a", "@20220625?a="),
+    ] {
+        let err = Tag::decode_str_canonical(encoded).unwrap_err();
+        let DecodeError::NonCanonical { canonical } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(expected_canonical, canonical);
+    }
+    assert!(matches!(
+        Tag::decode_str_canonical(" #label"),
+        Err(DecodeError::Parse(_) | DecodeError::InvalidTag)
+    ));
+}
+
+#[test]
+fn sort_with_facet_order() {
+    let mut decoded = DecodedTags::decode_str(
+        "#label archived@20220101 played@20220624 genre#techno wishlist#b played@20220625 wishlist#a",
+    );
+    decoded.sort_with_facet_order(&["wishlist", "played", "archived"]);
+    assert_eq!(
+        "wishlist#a wishlist#b played@20220625 played@20220624 archived@20220101 #label genre#techno",
+        decoded.reencode().unwrap()
+    );
+}
+
+#[test]
+fn decode_framing_only_input_should_fail() {
+    for encoded in ["#", "?", "?#", "??", "?&", "?&#", "?=", "?=#"] {
+        assert!(Tag::decode_str(encoded).is_err(), "{encoded}");
+        let decoded = DecodedTags::decode_str(encoded);
+        assert!(decoded.tags.is_empty(), "{encoded}");
+        assert_eq!(encoded, decoded.undecoded_prefix);
+    }
+}
+
+#[test]
+fn decode_framing_with_content_should_succeed() {
+    assert_eq!("#", Tag::decode_str("##").unwrap().label().as_ref());
+    assert_eq!("?", Tag::decode_str("#?").unwrap().label().as_ref());
+    assert!(Tag::decode_str("?#label").is_ok());
+    assert!(Tag::decode_str("@20220625?#").is_ok());
+}
+
+#[test]
+fn index_tags_by_label_and_facet_prefix() {
+    let library = [
+        DecodedTags::decode_str("#techno played@20220625 genre#house"),
+        DecodedTags::decode_str("genre#techno played@20220624 @20220101"),
+    ];
+    let tags = library.iter().flat_map(DecodedTags::tags);
+
+    let by_label = super::index_by_label(tags.clone());
+    assert_eq!(2, by_label.len());
+    assert_eq!(
+        vec!["#techno", "genre#techno"],
+        by_label["techno"]
+            .iter()
+            .map(|tag| tag.encode())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, by_label["house"].len());
+
+    let by_facet_prefix = super::index_by_facet_prefix(tags);
+    assert_eq!(2, by_facet_prefix.len());
+    assert_eq!(2, by_facet_prefix["genre"].len());
+    assert_eq!(
+        vec!["played@20220625", "played@20220624"],
+        by_facet_prefix["played"]
+            .iter()
+            .map(|tag| tag.encode())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn encoding_report() {
+    use encoding::{Component, EscapedChar};
+
+    let tag = Tag::decode_str("a%20b?k%3D=v%26w#l%C3%A4b%23el").unwrap();
+    let report = tag.encoding_report();
+    assert_eq!(
+        vec![
+            EscapedChar {
+                component: Component::Facet,
+                prop_index: None,
+                position: 1,
+                character: ' ',
+                escaped: "%20".into(),
+            },
+            EscapedChar {
+                component: Component::PropName,
+                prop_index: Some(0),
+                position: 1,
+                character: '=',
+                escaped: "%3D".into(),
+            },
+            EscapedChar {
+                component: Component::PropValue,
+                prop_index: Some(0),
+                position: 1,
+                character: '&',
+                escaped: "%26".into(),
+            },
+            EscapedChar {
+                component: Component::Label,
+                prop_index: None,
+                position: 1,
+                character: 'ä',
+                escaped: "%C3%A4".into(),
+            },
+        ],
+        report
+    );
+    assert!(Tag::decode_str("facet#label")
+        .unwrap()
+        .encoding_report()
+        .is_empty());
+}
+
+#[test]
+fn tags_older_than() {
+    let decoded = DecodedTags::decode_str(
+        "#label played@20220101 played@20220401 wishlist@20220402 played@20220625 @20220230?a=1",
+    );
+    let reference = Date::from_calendar_date(2022, time::Month::July, 1).unwrap();
+    assert_eq!(
+        vec!["played@20220101", "played@20220401"],
+        decoded
+            .tags_older_than(reference, 90)
+            .map(Tag::encode)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(4, decoded.tags_older_than(reference, 0).count());
+    assert_eq!(
+        0,
+        decoded
+            .tags_older_than(reference, i64::MAX / 86_400)
+            .count()
+    );
+    assert_eq!(0, decoded.tags_older_than(reference, i64::MAX).count());
+    assert_eq!(0, decoded.tags_older_than(reference, i64::MIN).count());
+    assert_eq!(
+        0,
+        decoded.tags_older_than(reference, -1_000_000_000).count()
+    );
+}
+
+#[test]
+fn decode_with_trimmed_prop_values() {
+    let encoded = "facet?a=%20value%09&b=%20&c=plain";
+    let tag = Tag::decode_str(encoded).unwrap();
+    assert_eq!(Some(&CompactString::from(" value\t")), tag.get_prop("a"));
+    let options = DecodeOptions {
+        trim_values: true,
+        ..Default::default()
+    };
+    let tag = Tag::decode_str_with_options(encoded, &options).unwrap();
+    assert_eq!(Some(&CompactString::from("value")), tag.get_prop("a"));
+    assert_eq!(Some(&CompactString::from("")), tag.get_prop("b"));
+    assert_eq!(Some(&CompactString::from("plain")), tag.get_prop("c"));
+    assert!(Tag::decode_str_with_options("facet?%20a=1", &options).is_err());
+}
+
+#[test]
+fn retain_latest_per_facet_prefix() {
+    let mut decoded = DecodedTags::decode_str(
+        "wishlist@20220101#alice #label wishlist@20220301#bob wishlist@20220201#alice \
+         played@20220625 played@20220624 wishlist@20220201?x=1#alice wishlist#alice @20220230?a=1",
+    );
+    assert_eq!(2, decoded.retain_latest_per_facet_prefix());
+    assert_eq!(0, decoded.clone().retain_latest_per_facet_prefix());
+    assert_eq!(
+        "#label wishlist@20220301#bob wishlist@20220201#alice played@20220625 \
+         wishlist@20220201?x=1#alice wishlist#alice @20220230?a=1",
+        decoded.reencode().unwrap()
+    );
+}
+
+#[test]
+fn search_decoded_and_encoded_content() {
+    let decoded = DecodedTags::decode_str("#a%20label #other facet?k=v%23w");
+    let search = |query| decoded.search(query).map(Tag::encode).collect::<Vec<_>>();
+    let search_encoded = |query| {
+        decoded
+            .search_encoded(query)
+            .map(Tag::encode)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["#a%20label"], search("a label"));
+    assert!(search("%20").is_empty());
+    assert_eq!(vec!["facet?k=v%23w"], search("v#w"));
+    assert_eq!(vec!["#a%20label"], search_encoded("%20"));
+    assert!(search_encoded("a label").is_empty());
+    assert_eq!(vec!["facet?k=v%23w"], search_encoded("?K="));
+    assert_eq!(3, decoded.search_encoded("").count());
+}
+
+#[test]
+fn props_with_name_prefix() {
+    let tag = Tag::decode_str("facet?audio.bpm=128&title=x&audio.key=8A&Audio.gain=1").unwrap();
+    assert_eq!(
+        vec!["audio.bpm=128", "audio.key=8A"],
+        tag.props_with_name_prefix("audio.")
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(4, tag.props_with_name_prefix("").count());
+    assert_eq!(0, tag.props_with_name_prefix("video.").count());
+}
+
+#[test]
+fn decoded_tags_to_rows() {
+    let decoded = DecodedTags::decode_str("#label facet?a=1&b=#other");
+    assert_eq!(
+        vec![
+            TagRow {
+                facet: "",
+                label: "label",
+                prop_name: None,
+                prop_value: None,
+            },
+            TagRow {
+                facet: "facet",
+                label: "other",
+                prop_name: Some("a"),
+                prop_value: Some("1"),
+            },
+            TagRow {
+                facet: "facet",
+                label: "other",
+                prop_name: Some("b"),
+                prop_value: Some(""),
+            },
+        ],
+        decoded.to_rows()
+    );
+    assert!(DecodedTags::decode_str("").to_rows().is_empty());
+}
+
+#[test]
+fn ord_tags_and_decoded_tags() {
+    let mut tags = DecodedTags::decode_str("@20220624 #b facet#a @20220625 #a").into_tags();
+    tags.sort();
+    let mut canonical = DecodedTags::decode_str("@20220624 #b facet#a @20220625 #a");
+    canonical.sort_by_policy(SortPolicy::Canonical);
+    assert_eq!(canonical.tags, tags);
+
+    let set = [
+        DecodedTags::decode_str("Text #b"),
+        DecodedTags::decode_str("#b"),
+        DecodedTags::decode_str("Text #a"),
+        DecodedTags::decode_str("#a #b"),
+    ]
+    .into_iter()
+    .collect::<std::collections::BTreeSet<_>>();
+    assert_eq!(
+        vec!["#a #b", "#b", "Text #a", "Text #b"],
+        set.into_iter()
+            .map(|decoded| decoded.reencode().unwrap())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn facet_segments() {
+    for (encoded, expected_segments, expected_date) in [
+        ("#label", vec![], None),
+        ("@20220625", vec![], Some("@20220625")),
+        ("genre#techno", vec!["genre"], None),
+        ("a/b/c@20220625", vec!["a", "b", "c"], Some("@20220625")),
+        ("a//b/@20220625", vec!["a", "b"], Some("@20220625")),
+        ("a b/c#label", vec!["a b", "c"], None),
+    ] {
+        let tag = Tag::decode_str(encoded).unwrap();
+        assert_eq!((expected_segments, expected_date), tag.facet_segments());
+    }
+}
+
+#[test]
+fn encode_tokens() {
+    let decoded = DecodedTags::decode_str("Some text #a facet?k=v%20w");
+    assert_eq!(
+        vec!["Some text ", "#a", "facet?k=v%20w"],
+        decoded.encode_tokens().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["#a", "#b"],
+        DecodedTags::decode_str("#a #b")
+            .encode_tokens()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(0, DecodedTags::decode_str("").encode_tokens().count());
+}
+
+#[test]
+fn decode_props_without_facet_and_label_should_fail() {
+    for encoded in ["?name=val", "?name=val#", "?name", "?a=1&b=2"] {
+        assert!(
+            matches!(Tag::decode_str(encoded), Err(DecodeError::InvalidTag)),
+            "{encoded}"
+        );
+    }
+    // Valid with either a label or a facet
+    assert!(Tag::decode_str("?name=val#label").is_ok());
+    assert!(Tag::decode_str("facet?name=val").is_ok());
+}
+
+#[test]
+fn set_date_suffix() {
+    let date = Date::from_calendar_date(2022, time::Month::June, 25).unwrap();
+    for (encoded, default_prefix, expected) in [
+        ("#label", None, "@20220625#label"),
+        ("#label", Some("dated"), "dated@20220625#label"),
+        ("@20220101#label", Some("dated"), "dated@20220625#label"),
+        ("played@20220101", Some("dated"), "played@20220625"),
+        ("genre?k=v", None, "genre@20220625?k=v"),
+    ] {
+        let mut tag = Tag::decode_str(encoded).unwrap();
+        tag.set_date_suffix(date, default_prefix).unwrap();
+        assert_eq!(expected, tag.encode());
+    }
+
+    let mut tag = Tag::decode_str("#label").unwrap();
+    assert!(tag.set_date_suffix(date, Some("/dated")).is_err());
+    assert!(tag.set_date_suffix(date, Some("dated ")).is_err());
+    // Negative years are not supported
+    assert!(tag.set_date_suffix(Date::MIN, None).is_err());
+    assert_eq!("#label", tag.encode());
+}
+
+#[test]
+fn property_value_presence() {
+    let tag = Tag::decode_str("facet?a=1&b=&c").unwrap();
+    let [a, b, c] = tag.props() else {
+        panic!("unexpected properties");
+    };
+    assert!(a.has_value());
+    assert!(!a.is_empty_value());
+    assert!(!b.has_value());
+    assert!(b.is_empty_value());
+    assert!(!c.has_value());
+    assert!(c.is_empty_value());
+}
+
+#[test]
+fn decode_tag_ref_same_as_decode_tag() {
+    for encoded in [
+        "#label",
+        "facet#label",
+        "facet?name=value#label",
+        "facet@20220625",
+        "facet?a=1&b=&c",
+        "facet@2022062",
+        "?name=value",
+        "facet",
+        "a/b?c=d#e",
+        "a%20b?c%3Dd=e%26f#g%23h",
+        "a%2Fb#l%C3%A4bel",
+        "l%C3%A4bel",
+        "#l%FFbel",
+        "sch:facet#label",
+        "https://example.com",
+        "a/./b#label",
+        "a/../b#label",
+        "%2E%2e/b#label",
+        "./#label",
+        "fa\tcet#label",
+        "\\facet#label",
+        "\\#label",
+        "\\\\host/x#l",
+        "a\\b#label",
+        "a/\\..\\b#label",
+        "facet?name=\\value#\\label",
+        "a b#label",
+        "facet# label",
+        "facet#label ",
+        "facet? a=b#label",
+        "a%20b?c=%20d#l%20",
+        "facet%3f?a=b",
+        "facet#lab\0el",
+        "facet?name=value=value#label",
+        "facet?=value#label",
+        "#label#label",
+        "##",
+        "#",
+        "/facet#label",
+        " #label",
+        "",
+    ] {
+        let expected = Tag::decode_str(encoded).ok();
+        let actual = TagRef::decode_str(encoded).ok().map(TagRef::into_owned);
+        assert_eq!(expected, actual, "{encoded}");
+    }
+}
+
+#[test]
+fn decode_tag_ref_borrows_unescaped_components() {
+    let encoded = "facet?name=value#label";
+    let tag = TagRef::decode_str(encoded).unwrap();
+    assert!(tag.is_borrowed());
+    assert_eq!("facet", tag.facet);
+    assert_eq!("label", tag.label);
+
+    let tag = TagRef::decode_str("facet?name=value#l%C3%A4bel").unwrap();
+    assert!(!tag.is_borrowed());
+    assert!(matches!(tag.facet, Cow::Borrowed("facet")));
+    assert_eq!("läbel", tag.label);
+}
+
+#[test]
+fn decode_tags_ref_same_as_decode_tags() {
+    for encoded in [
+        "",
+        "   ",
+        "#label",
+        "Some text\n\n#label facet@20220625 other?name=value",
+        "prefix #invalid# facet?name=value#label\t",
+        "\u{feff}#label",
+        "first line\n#label\n",
+        "text\u{a0}#label a/./b#dotted",
+        "foo:bar #label",
+        "text \\facet#label",
+    ] {
+        let expected = DecodedTags::decode_str(encoded);
+        let actual = DecodedTagsRef::decode_str(encoded).into_owned();
+        assert_eq!(expected, actual, "{encoded:?}");
+    }
+    let encoded = "Some text #label facet?name=value";
+    let decoded = DecodedTagsRef::decode_str(encoded);
+    assert_eq!(2, decoded.tags.len());
+    assert!(decoded.tags.iter().all(TagRef::is_borrowed));
+    assert_eq!("Some text ", decoded.undecoded_prefix);
+}